@@ -1,55 +1,71 @@
 use anyhow::Result;
+use futures::stream::{self, StreamExt};
 use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
 
 use crate::database::Database;
-use crate::config::Config;
+use crate::config::{Config, DEFAULT_CLUSTER};
 use crate::github::{GitHubClient, RepositoryService};
-use crate::docker::{DockerClient, ImageService, StackService};
+use crate::docker::{ClusterServices, Clusters};
+use crate::dependency_graph::DependencyGraph;
+use crate::manifest_processor::ManifestProcessor;
+use crate::lint_processor::LintProcessor;
+use crate::models::{Deployment, Stack};
+use crate::report::{Outcome, ReconcileReport};
 use crate::stack_processor::StackProcessor;
 
 pub struct Commands {
     db: Arc<Database>,
-    repo_service: RepositoryService,
-    image_service: Arc<ImageService>,
-    stack_service: Arc<StackService>,
+    repo_service: Arc<RepositoryService>,
+    clusters: Clusters,
     config: Config,
 }
 
 impl Commands {
     pub async fn new(db: Database) -> Result<Self> {
-        let config = Config::from_env();
-        let github_client = GitHubClient::new()?;
-        let docker_client = DockerClient::new().await?;
-        let repo_service = RepositoryService::new(github_client);
-        let image_service = Arc::new(ImageService::new(docker_client, config.image_pull_policy));
-        
-        // Create a new Docker client for stack service
-        let docker_client_for_stacks = DockerClient::new().await?;
-        let stack_service = Arc::new(StackService::new(docker_client_for_stacks));
+        let config = Config::from_env()?;
+        let repo_service = Arc::new(RepositoryService::new(GitHubClient::new()?));
+        let clusters = Clusters::connect(&config).await?;
         
         Ok(Self {
             db: Arc::new(db),
             repo_service,
-            image_service,
-            stack_service,
+            clusters,
             config,
         })
     }
 
+    fn stack_processor(&self, cluster: &ClusterServices) -> StackProcessor {
+        StackProcessor::new(
+            Arc::clone(&self.db),
+            Arc::clone(&self.repo_service),
+            cluster.clone(),
+            self.config.clone(),
+        )
+    }
+
+    /// Services du cluster `default`, qui gère le cycle de vie des images référencées
+    fn default_cluster(&self) -> Result<&ClusterServices> {
+        self.clusters.get(DEFAULT_CLUSTER)
+    }
+
     /// Returns true if the repo was already in cache (caller may ignore).
     pub async fn watch_or_skip_if_cached(&self, github_url: &str) -> Result<bool> {
         if let Some(cached_repo) = self.db.get_repository_from_cache(github_url).await? {
             println!("Repository '{}' is already being watched (last watch: {}), skipping.", github_url, cached_repo.last_watch);
             return Ok(true);
         }
-        self.watch(github_url).await?;
+        let report = self.watch(github_url).await?;
+        if report.has_failures() {
+            eprintln!("Warning: {} items failed while watching '{}'", report.failure_count(), github_url);
+        }
         Ok(false)
     }
 
-    pub async fn watch(&self, github_url: &str) -> Result<()> {
+    pub async fn watch(&self, github_url: &str) -> Result<ReconcileReport> {
         println!("Watching GitHub repository: {}", github_url);
         
         // Check if repository is already in cache
@@ -58,35 +74,30 @@ impl Commands {
                 github_url, cached_repo.last_watch));
         }
         
-        // Clone the repository
-        let repo_path = self.repo_service.clone_repository(github_url).await?;
-        println!("Repository cloned to: {}", repo_path);
-        
-        // Process stacks and deploy them
-        let stack_processor = StackProcessor::new(
-            Arc::clone(&self.db),
-            Arc::clone(&self.image_service),
-            Arc::clone(&self.stack_service),
-        );
-        stack_processor.process_and_deploy_stacks(&repo_path, github_url, false, false).await?;
-        
-        // Process images: pull according to policy, remove unused
-        self.process_images().await?;
+        // Clone, process stacks and deploy them
+        let mut report = self.reconcile_repository(github_url, false, None, None, true).await;
+        if report.repository_failed(github_url) {
+            // The repository itself could not be processed: do not start watching it
+            report.print_summary();
+            return Err(anyhow::anyhow!("Failed to watch repository '{}'", github_url));
+        }
+
+        // Process images: pull according to policy
+        report.extend(self.process_images(false).await?);
         
-        // Add repository to cache
+        // Add repository to cache, even if some stacks failed, so that reconcile retries them
         self.db.add_repository_to_cache(github_url).await?;
         println!("Repository added to cache");
-        
-        // Clean up cloned repository
-        if let Err(e) = fs::remove_dir_all(&repo_path) {
-            println!("Warning: Could not clean up repository directory: {}", e);
-        }
-        
-        Ok(())
+
+        report.print_summary();
+        Ok(report)
     }
 
-    pub async fn reconcile(&self, force: bool) -> Result<()> {
+    pub async fn reconcile(&self, force: bool) -> Result<ReconcileReport> {
         println!("Reconciling database...");
+        if let Some(environment) = &self.config.environment {
+            println!("Environment: {}", environment);
+        }
         
         // Check if there are any repositories in cache
         let repositories = self.db.get_all_repositories().await?;
@@ -104,9 +115,22 @@ impl Commands {
         println!("\nFound {} stacks in database:", stacks.len());
         
         for stack in &stacks {
-            println!("  - {} (status: {}, hash: {})", stack.name, stack.status, stack.hash);
+            let pinned = if stack.pinned { ", pinned" } else { "" };
+            let cluster = if stack.cluster != DEFAULT_CLUSTER { format!(", cluster: {}", stack.cluster) } else { String::new() };
+            println!("  - {} (status: {}, hash: {}{}{})", stack.name, stack.status, stack.hash, cluster, pinned);
         }
         
+        // Expired suspensions no longer apply: drop them instead of filtering them on every lookup
+        self.db.delete_expired_suspensions().await?;
+        let suspensions = self.db.get_active_suspensions().await?;
+        if !suspensions.is_empty() {
+            println!("\nFound {} active suspensions:", suspensions.len());
+            for suspension in &suspensions {
+                let reason = suspension.reason.as_deref().map(|r| format!(", reason: {}", r)).unwrap_or_default();
+                println!("  - {} '{}' (until {}{})", suspension.kind, suspension.target, suspension.expires_at.as_deref().unwrap_or("resumed"), reason);
+            }
+        }
+
         // Get all images and display them
         let images = self.db.get_all_images().await?;
         println!("\nFound {} images in database:", images.len());
@@ -120,32 +144,136 @@ impl Commands {
         if force {
             println!("⚠️  Force mode enabled - will redeploy all stacks regardless of changes");
         }
-        for repo in &repositories {
-            println!("Reconciling repository: {}", repo.url);
-            
-            // Clone the repository
-            let repo_path = self.repo_service.clone_repository(&repo.url).await?;
-            println!("Repository cloned to: {}", repo_path);
-            
-            // Process stacks and deploy them (with is_reconcile=true and force flag)
-            let stack_processor = StackProcessor::new(
-                Arc::clone(&self.db),
-                Arc::clone(&self.image_service),
-                Arc::clone(&self.stack_service),
-            );
-            stack_processor.process_and_deploy_stacks(&repo_path, &repo.url, true, force).await?;
-            
-            // Process images: pull according to policy, remove unused
-            self.process_images().await?;
-            
-            // Clean up cloned repository
-            if let Err(e) = fs::remove_dir_all(&repo_path) {
-                println!("Warning: Could not clean up repository directory: {}", e);
+
+        // Image references are recounted across every repository
+        self.db.reset_image_reference_counts().await?;
+
+        // Repositories are processed in parallel, up to repo_concurrency at a time
+        let repo_reports: Vec<ReconcileReport> = stream::iter(&repositories)
+            .map(|repo| self.reconcile_repository(&repo.url, force, None, None, true))
+            .buffered(self.config.repo_concurrency)
+            .collect()
+            .await;
+
+        let mut report = ReconcileReport::new();
+        let mut all_repositories_processed = true;
+        for (repo, repo_report) in repositories.iter().zip(repo_reports) {
+            if repo_report.repository_failed(&repo.url) {
+                all_repositories_processed = false;
             }
+            report.extend(repo_report);
         }
-        
+
+        // Unused images can only be identified when every repository has been counted
+        if !all_repositories_processed {
+            println!("Skipping removal of unused images: some repositories could not be processed");
+        }
+        match self.process_images(all_repositories_processed).await {
+            Ok(images_report) => report.extend(images_report),
+            Err(e) => report.record("images", None, Outcome::Failed(format!("{:#}", e))),
+        }
+
         println!("Reconciliation completed!");
-        Ok(())
+        report.print_summary();
+        Ok(report)
+    }
+
+    /// Réconcilie un seul repository (ou un seul de ses stacks), éventuellement à une révision donnée
+    pub async fn sync(&self, repo_url: &str, stack: Option<&str>, revision: Option<&str>, force: bool) -> Result<ReconcileReport> {
+        println!("Syncing repository: {}", repo_url);
+
+        // Image references are only counted by a full reconcile, after resetting every count
+        let mut report = self.reconcile_repository(repo_url, force, stack, revision, false).await;
+
+        // Without every repository counted, unused images cannot be removed: only pull
+        match self.process_images(false).await {
+            Ok(images_report) => report.extend(images_report),
+            Err(e) => report.record("images", None, Outcome::Failed(format!("{:#}", e))),
+        }
+
+        println!("Sync completed!");
+        report.print_summary();
+        Ok(report)
+    }
+
+    /// Clone un repository et traite ses stacks ; les erreurs sont consignées dans le rapport,
+    /// au niveau du repository (entrée sans stack) si le repository lui-même n'a pas pu être traité.
+    /// Avec `count_images`, les références d'images de ses stacks sont ajoutées aux compteurs.
+    async fn reconcile_repository(
+        &self,
+        repo_url: &str,
+        force: bool,
+        stack: Option<&str>,
+        revision: Option<&str>,
+        count_images: bool,
+    ) -> ReconcileReport {
+        println!("Reconciling repository: {}", repo_url);
+
+        let repo_path = match self.repo_service.clone_repository(repo_url).await {
+            Ok(repo_path) => repo_path,
+            Err(e) => {
+                eprintln!("Error cloning repository '{}': {:#}", repo_url, e);
+                let mut report = ReconcileReport::new();
+                report.record(repo_url, None, Outcome::Failed(format!("{:#}", e)));
+                return report;
+            }
+        };
+        println!("Repository cloned to: {}", repo_path);
+
+        let result = self.process_cloned(&repo_path, repo_url, force, stack, revision, count_images).await;
+
+        // Clean up cloned repository
+        if let Err(e) = fs::remove_dir_all(&repo_path) {
+            println!("Warning: Could not clean up repository directory: {}", e);
+        }
+
+        result.unwrap_or_else(|e| {
+            eprintln!("Error processing repository '{}': {:#}", repo_url, e);
+            let mut report = ReconcileReport::new();
+            report.record(repo_url, None, Outcome::Failed(format!("{:#}", e)));
+            report
+        })
+    }
+
+    async fn process_cloned(
+        &self,
+        repo_path: &str,
+        repo_url: &str,
+        force: bool,
+        stack: Option<&str>,
+        revision: Option<&str>,
+        count_images: bool,
+    ) -> Result<ReconcileReport> {
+        if let Some(revision) = revision {
+            let commit = RepositoryService::checkout_revision(repo_path, revision)?;
+            println!("Checked out revision '{}' ({})", revision, commit);
+        }
+
+        let clusters = self.target_clusters(repo_path, repo_url);
+        let mut report = ReconcileReport::new();
+        for cluster in &clusters {
+            if clusters.len() > 1 {
+                println!("Deploying to cluster '{}'", cluster);
+            }
+            let stack_processor = self.stack_processor(self.clusters.get(cluster)?);
+            let cluster_report = stack_processor.process_and_deploy_stacks(repo_path, repo_url, force, stack, count_images).await?;
+            report.extend(cluster_report.with_cluster(cluster));
+        }
+        Ok(report)
+    }
+
+    /// Clusters ciblés par un repository : ceux auxquels il est affecté et ceux que désignent ses stacks
+    fn target_clusters(&self, repo_path: &str, repo_url: &str) -> Vec<String> {
+        let repository_clusters = self.config.repository_clusters(repo_url);
+        // An unreadable manifest is reported by the stack processor
+        let stack_clusters: Vec<String> = ManifestProcessor::load(Path::new(repo_path), self.config.environment.as_deref(), self.config.discovery.as_ref())
+            .map(|manifest| manifest.stacks.into_iter().filter_map(|s| s.cluster).collect())
+            .unwrap_or_default();
+
+        self.config.clusters.iter()
+            .filter(|c| repository_clusters.contains(&c.name.as_str()) || stack_clusters.contains(&c.name))
+            .map(|c| c.name.clone())
+            .collect()
     }
 
     pub async fn stop(&self) -> Result<()> {
@@ -155,10 +283,11 @@ impl Commands {
         let stacks = self.db.get_all_stacks().await?;
         println!("Found {} stacks to remove", stacks.len());
         
-        // Remove all stacks
-        for stack in &stacks {
+        // Remove all stacks, dependents before their dependencies
+        for stack in Self::removal_order(&stacks) {
             println!("Removing stack: {}", stack.name);
-            self.stack_service.stop_stack(&stack.name).await?;
+            let cluster = self.clusters.get(&stack.cluster)?;
+            cluster.stack_service.stop_stack(stack.deploy_backend(), &stack.name).await?;
         }
         
         // Get all images from database
@@ -166,9 +295,10 @@ impl Commands {
         println!("Found {} images to remove", images.len());
         
         // Remove all images
+        let image_service = &self.default_cluster()?.image_service;
         for image in &images {
             println!("Removing image: {}", image.name);
-            self.image_service.remove_image(&image.name).await?;
+            image_service.remove_image(&image.name).await?;
         }
         
         // Clean up database
@@ -201,6 +331,86 @@ impl Commands {
         Ok(())
     }
 
+    /// Ordonne les stacks pour la suppression : par repository et cluster, en ordre inverse des dépendances
+    fn removal_order(stacks: &[Stack]) -> Vec<&Stack> {
+        let mut ordered = Vec::new();
+        let mut repositories: Vec<(&str, &str)> = stacks.iter().map(|s| (s.repository_url.as_str(), s.cluster.as_str())).collect();
+        repositories.sort();
+        repositories.dedup();
+
+        for (repository_url, cluster) in repositories {
+            let repo_stacks: Vec<&Stack> = stacks.iter().filter(|s| s.repository_url == repository_url && s.cluster == cluster).collect();
+            let names: Vec<&str> = repo_stacks.iter().map(|s| s.name.as_str()).collect();
+            let nodes = repo_stacks.iter()
+                .map(|s| {
+                    let deps = s.depends_on.split(',')
+                        .filter(|d| names.contains(d))
+                        .map(|d| d.to_string())
+                        .collect();
+                    (s.name.clone(), deps)
+                })
+                .collect();
+
+            match DependencyGraph::new(nodes).and_then(|graph| graph.removal_order()) {
+                Ok(order) => ordered.extend(order.iter().filter_map(|name| repo_stacks.iter().find(|s| &s.name == name).copied())),
+                Err(e) => {
+                    println!("Warning: Could not order stacks of {} by dependencies: {}", repository_url, e);
+                    ordered.extend(repo_stacks);
+                }
+            }
+        }
+
+        ordered
+    }
+
+    /// Affiche le graphe de dépendances des stacks d'un repository local au format DOT
+    pub fn graph(repo_path: &str) -> Result<()> {
+        let config = Config::from_env()?;
+        let manifest = ManifestProcessor::load(Path::new(repo_path), config.environment.as_deref(), config.discovery.as_ref())?;
+
+        let graph = DependencyGraph::from_definitions(&manifest.stacks)?;
+        // Fail on cycles rather than printing an undeployable graph
+        graph.levels()?;
+        print!("{}", graph.to_dot());
+        Ok(())
+    }
+
+    /// Valide les manifestes et fichiers compose d'un repository local ; retourne false en cas de problème
+    pub fn lint(repo_path: &str) -> Result<bool> {
+        let config = Config::from_env()?;
+        let report = LintProcessor::lint(Path::new(repo_path), config.environment.as_deref(), config.discovery.as_ref());
+
+        for issue in &report.issues {
+            println!("{}", issue);
+        }
+        if report.issues.is_empty() {
+            println!("No problems found ({} files checked)", report.checked_files.len());
+        } else {
+            println!("{} problem(s) found", report.issues.len());
+        }
+        Ok(report.issues.is_empty())
+    }
+
+    /// Convertit stacks.yaml, volumes.yaml, nfs.yaml et les secrets.yaml des stacks en dockerops.yaml
+    pub fn migrate_manifest(repo_path: &str, force: bool) -> Result<()> {
+        let migration = ManifestProcessor::migrate(Path::new(repo_path), force)?;
+        println!("Wrote {}", migration.manifest_path.display());
+
+        if !migration.replaced_files.is_empty() {
+            println!("These files are no longer read and can be removed:");
+            for path in &migration.replaced_files {
+                println!("  - {}", path.display());
+            }
+        }
+        if !migration.overlay_files.is_empty() {
+            println!("Warning: environment overlays were not migrated; rewrite them as dockerops.<env>.yaml:");
+            for path in &migration.overlay_files {
+                println!("  - {}", path.display());
+            }
+        }
+        Ok(())
+    }
+
     pub fn show_version() {
         println!("DockerOps CLI v{}", env!("CARGO_PKG_VERSION"));
         println!("A Docker Swarm stack manager for GitHub repositories");
@@ -208,7 +418,7 @@ impl Commands {
     }
 
     /// Daemon mode: optionally seed repos from DOCKEROPS_REPOS, then reconcile in a loop every interval_secs.
-    pub async fn run_daemon(&self, repo_urls: &[String], interval_secs: u64) -> Result<()> {
+    pub async fn run_daemon(&self, repo_urls: &[String], interval_secs: u64, image_watch_secs: Option<u64>) -> Result<()> {
         for url in repo_urls {
             let url = url.trim();
             if url.is_empty() {
@@ -219,14 +429,217 @@ impl Commands {
             }
         }
 
-        let duration = Duration::from_secs(interval_secs);
+        // A zero period would make the timers panic
+        let duration = Duration::from_secs(interval_secs.max(1));
+        let mut reconcile_ticks = interval_at(Instant::now() + duration, duration);
+        reconcile_ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // Without an image watch interval, its branch below is disabled
+        let watch_duration = image_watch_secs.map(Duration::from_secs).unwrap_or(duration);
+        let mut image_watch_ticks = interval_at(Instant::now() + watch_duration, watch_duration);
+        image_watch_ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
         loop {
-            sleep(duration).await;
-            println!("[daemon] Running reconcile (interval {}s)...", interval_secs);
-            if let Err(e) = self.reconcile(false).await {
-                eprintln!("[daemon] Reconcile error: {}", e);
+            tokio::select! {
+                _ = reconcile_ticks.tick() => {
+                    println!("[daemon] Running reconcile (interval {}s)...", interval_secs);
+                    match self.reconcile(false).await {
+                        Ok(report) if report.has_failures() => {
+                            eprintln!("[daemon] Reconcile finished with {} failed items", report.failure_count());
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("[daemon] Reconcile error: {}", e),
+                    }
+                }
+                _ = image_watch_ticks.tick(), if image_watch_secs.is_some() => {
+                    println!("[daemon] Checking image digests (interval {}s)...", watch_duration.as_secs());
+                    match self.check_images(None).await {
+                        Ok(report) if report.has_failures() => {
+                            eprintln!("[daemon] Image check finished with {} failed items", report.failure_count());
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("[daemon] Image check error: {}", e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Met à jour les services des stacks déployés dont un tag d'image a été déplacé dans son registry
+    pub async fn check_images(&self, stack_filter: Option<&str>) -> Result<ReconcileReport> {
+        let stacks: Vec<Stack> = self.db.get_all_stacks().await?
+            .into_iter()
+            .filter(|s| stack_filter.is_none_or(|name| s.name == name))
+            .collect();
+        if let (Some(name), true) = (stack_filter, stacks.is_empty()) {
+            return Err(anyhow::anyhow!("Stack '{}' not found", name));
+        }
+
+        let mut report = ReconcileReport::new();
+        for stack in &stacks {
+            let outcome = match self.clusters.get(&stack.cluster) {
+                Ok(cluster) => self.stack_processor(cluster).refresh_images(stack).await
+                    .unwrap_or_else(|e| Outcome::Failed(format!("{:#}", e))),
+                Err(e) => Outcome::Failed(format!("{:#}", e)),
+            };
+            let mut stack_report = ReconcileReport::new();
+            stack_report.record(&stack.repository_url, Some(&stack.name), outcome);
+            report.extend(stack_report.with_cluster(&stack.cluster));
+        }
+
+        report.print_summary();
+        Ok(report)
+    }
+
+    /// Affiche l'historique des déploiements, du plus récent au plus ancien
+    pub async fn history(&self, stack: Option<&str>, repo: Option<&str>, cluster: Option<&str>, limit: i64) -> Result<()> {
+        let deployments = self.db.get_deployments(stack, repo, cluster, limit).await?;
+        if deployments.is_empty() {
+            println!("No deployments recorded.");
+            return Ok(());
+        }
+
+        println!("{:<6} {:<24} {:<10} {:<10} {:<26} {:<26} {:<12} REPOSITORY", "ID", "STACK", "COMMIT", "STATUS", "STARTED", "FINISHED", "CLUSTER");
+        for deployment in &deployments {
+            let commit = deployment.commit_sha.as_deref()
+                .map(|sha| sha.chars().take(8).collect::<String>())
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<6} {:<24} {:<10} {:<10} {:<26} {:<26} {:<12} {}",
+                deployment.id,
+                deployment.stack_name,
+                commit,
+                deployment.status,
+                deployment.started_at,
+                deployment.finished_at.as_deref().unwrap_or("-"),
+                deployment.cluster,
+                deployment.repository_url,
+            );
+            if let Some(error) = &deployment.error {
+                println!("       error: {}", error.trim());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Redéploie un déploiement précédent d'un stack et l'épingle
+    pub async fn rollback(&self, stack_name: &str, to: Option<&str>, repo: Option<&str>, cluster: Option<&str>) -> Result<()> {
+        let stack = self.resolve_stack(stack_name, repo, cluster).await?;
+        let target = self.resolve_rollback_target(&stack, to).await?;
+
+        let stack_processor = self.stack_processor(self.clusters.get(&stack.cluster)?);
+        stack_processor.rollback_stack(&stack, &target).await
+    }
+
+    /// Retire l'épinglage d'un stack : le prochain reconcile le redéploie depuis le repository
+    pub async fn unpin(&self, stack_name: &str, repo: Option<&str>, cluster: Option<&str>) -> Result<()> {
+        let stack = self.resolve_stack(stack_name, repo, cluster).await?;
+        if !stack.pinned {
+            println!("Stack '{}' is not pinned.", stack.name);
+            return Ok(());
+        }
+
+        self.db.set_stack_pinned(&stack.name, &stack.repository_url, &stack.cluster, false).await?;
+        println!("Stack '{}' unpinned; it will be reconciled from {} on the next run.", stack.name, stack.repository_url);
+        Ok(())
+    }
+
+    /// Suspend la réconciliation d'un stack ou d'un repository, éventuellement pour une durée limitée
+    pub async fn suspend(&self, target: &str, ttl: Option<Duration>, reason: Option<&str>) -> Result<()> {
+        let kind = if self.db.get_repository_from_cache(target).await?.is_some() {
+            "repository"
+        } else if !self.db.get_stacks_by_name(target).await?.is_empty() {
+            "stack"
+        } else {
+            return Err(anyhow::anyhow!("'{}' is neither a watched repository nor a known stack", target));
+        };
+
+        let expires_at = ttl
+            .map(|ttl| chrono::Duration::from_std(ttl).map(|ttl| (chrono::Utc::now() + ttl).to_rfc3339()))
+            .transpose()?;
+        self.db.suspend(target, kind, reason, expires_at.as_deref()).await?;
+
+        match &expires_at {
+            Some(expires_at) => println!("Suspended {} '{}' until {}", kind, target, expires_at),
+            None => println!("Suspended {} '{}' until resumed", kind, target),
+        }
+        Ok(())
+    }
+
+    /// Reprend la réconciliation d'un stack ou d'un repository suspendu
+    pub async fn resume(&self, target: &str) -> Result<()> {
+        // Same resolution as suspend: a watched repository URL, otherwise a stack name
+        let kind = if self.db.get_repository_from_cache(target).await?.is_some() {
+            "repository"
+        } else {
+            "stack"
+        };
+
+        if self.db.resume(target, kind).await? {
+            println!("Resumed '{}'", target);
+        } else {
+            println!("'{}' is not suspended", target);
+        }
+        Ok(())
+    }
+
+    /// Retrouve un stack par nom, en désambiguïsant par repository et par cluster si besoin
+    async fn resolve_stack(&self, stack_name: &str, repo: Option<&str>, cluster: Option<&str>) -> Result<Stack> {
+        let mut stacks = self.db.get_stacks_by_name(stack_name).await?;
+        if let Some(repo) = repo {
+            stacks.retain(|s| s.repository_url == repo);
+        }
+        if let Some(cluster) = cluster {
+            stacks.retain(|s| s.cluster == cluster);
+        }
+
+        match stacks.len() {
+            0 => Err(anyhow::anyhow!("Stack '{}' not found in database", stack_name)),
+            1 => Ok(stacks.remove(0)),
+            _ if stacks.iter().all(|s| s.repository_url == stacks[0].repository_url) => Err(anyhow::anyhow!(
+                "Stack '{}' is deployed to several clusters ({}), use --cluster to choose one",
+                stack_name,
+                stacks.iter().map(|s| s.cluster.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Stack '{}' exists in several repositories ({}), use --repo to choose one",
+                stack_name,
+                stacks.iter().map(|s| s.repository_url.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    /// Détermine le déploiement cible d'un rollback : un ID, un commit (préfixe accepté) ou, par défaut, le déploiement réussi précédent
+    async fn resolve_rollback_target(&self, stack: &Stack, to: Option<&str>) -> Result<Deployment> {
+        if let Some(to) = to {
+            if let Ok(id) = to.parse::<i64>() {
+                if let Some(deployment) = self.db.get_deployment(id).await? {
+                    if deployment.stack_name != stack.name || deployment.repository_url != stack.repository_url || deployment.cluster != stack.cluster {
+                        return Err(anyhow::anyhow!("Deployment #{} belongs to stack '{}', not '{}'", id, deployment.stack_name, stack.name));
+                    }
+                    if deployment.status != "succeeded" {
+                        return Err(anyhow::anyhow!("Deployment #{} did not succeed (status: {})", id, deployment.status));
+                    }
+                    return Ok(deployment);
+                }
             }
+
+            return self.db.get_successful_deployments(&stack.name, &stack.repository_url, &stack.cluster).await?
+                .into_iter()
+                .find(|d| d.commit_sha.as_deref().is_some_and(|sha| sha.starts_with(to)))
+                .ok_or_else(|| anyhow::anyhow!("No successful deployment of stack '{}' matches '{}'", stack.name, to));
         }
+
+        // Without --to, skip the deployment currently running if the latest attempt succeeded
+        let latest_succeeded = self.db.get_deployments(Some(&stack.name), Some(&stack.repository_url), Some(&stack.cluster), 1).await?
+            .first()
+            .is_some_and(|d| d.status == "succeeded");
+        let skip = if latest_succeeded { 1 } else { 0 };
+
+        self.db.get_successful_deployments(&stack.name, &stack.repository_url, &stack.cluster).await?
+            .into_iter()
+            .nth(skip)
+            .ok_or_else(|| anyhow::anyhow!("No previous successful deployment recorded for stack '{}'", stack.name))
     }
 
     pub async fn debug_cache(&self) -> Result<()> {
@@ -242,26 +655,45 @@ impl Commands {
         Ok(())
     }
 
-    async fn process_images(&self) -> Result<()> {
+    /// Pull les images référencées selon la policy et, si `remove_unused`, supprime celles qui ne sont plus référencées.
+    /// Seul le cluster `default` est concerné : les autres pullent leurs images au déploiement.
+    async fn process_images(&self, remove_unused: bool) -> Result<ReconcileReport> {
+        let mut report = ReconcileReport::new();
+        let image_service = &self.default_cluster()?.image_service;
+
         // Get all images from database
         let images = self.db.get_all_images().await?;
         println!("  Found {} images in database", images.len());
         
-        for image in &images {
-            if image.reference_count == 0 {
+        if remove_unused {
+            for image in images.iter().filter(|image| image.reference_count == 0) {
                 // Remove unused images
                 println!("  Removing unused image: {}", image.name);
-                self.image_service.remove_image(&image.name).await?;
-            } else {
-                // Pull image according to policy (Always or IfNotPresent)
+                image_service.remove_image(&image.name).await?;
+            }
+        }
+
+        // Pull referenced images according to policy (Always or IfNotPresent), `pull_concurrency` at a time
+        let pulls: Vec<(&str, Result<()>)> = stream::iter(images.iter().filter(|image| image.reference_count > 0))
+            .map(|image| async move {
                 println!("  Processing image: {} (referenced {} times)", image.name, image.reference_count);
-                self.image_service.pull_image(&image.name).await?;
+                (image.name.as_str(), image_service.pull_image(&image.name).await)
+            })
+            .buffer_unordered(self.config.pull_concurrency)
+            .collect()
+            .await;
+        for (image_name, result) in pulls {
+            if let Err(e) = result {
+                eprintln!("  Error pulling image '{}': {:#}", image_name, e);
+                report.record("images", Some(image_name), Outcome::Failed(format!("{:#}", e)));
             }
         }
         
         // Remove images with zero count from database
-        self.db.delete_images_with_zero_count().await?;
+        if remove_unused {
+            self.db.delete_images_with_zero_count().await?;
+        }
         
-        Ok(())
+        Ok(report)
     }
 }
@@ -1,6 +1,7 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use serde_yaml::{Value, Mapping};
 use crate::models::VolumeDefinition;
 use crate::models::NfsConfig;
@@ -51,6 +52,435 @@ impl ComposeProcessor {
         }
     }
 
+    /// Image de chaque service du compose (service -> image)
+    pub fn service_images(content: &str) -> Result<BTreeMap<String, String>> {
+        let yaml_value: Value = serde_yaml::from_str(content)?;
+        let services = yaml_value.get("services").and_then(|s| s.as_mapping()).into_iter().flatten();
+        Ok(services
+            .filter_map(|(name, service)| {
+                let image = service.get("image")?.as_str()?;
+                Some((name.as_str()?.to_string(), image.to_string()))
+            })
+            .collect())
+    }
+
+    /// Remplace les images des services par des références épinglées `image@digest`
+    pub fn pin_images(content: &str, image_digests: &BTreeMap<String, String>) -> Result<String> {
+        let mut yaml_value: Value = serde_yaml::from_str(content)?;
+
+        if let Some(services) = yaml_value.get_mut("services").and_then(|s| s.as_mapping_mut()) {
+            for (_service_name, service) in services {
+                let image = match service.get("image").and_then(|i| i.as_str()) {
+                    Some(image) => image.to_string(),
+                    None => continue,
+                };
+                if let Some(digest) = image_digests.get(&image) {
+                    let repository = image.split('@').next().unwrap_or(&image);
+                    service["image"] = Value::String(format!("{}@{}", repository, digest));
+                }
+            }
+        }
+
+        Ok(serde_yaml::to_string(&yaml_value)?)
+    }
+
+    /// Fusionne plusieurs fichiers compose dans l'ordre, selon les règles de fusion de Compose :
+    /// les mappings sont fusionnés récursivement, les valeurs simples sont remplacées par le fichier suivant,
+    /// et les tags `!reset` / `!override` sont respectés.
+    pub fn merge(documents: &[String]) -> Result<String> {
+        let mut merged = Value::Mapping(Mapping::new());
+        let mut path = Vec::new();
+        for document in documents {
+            let value: Value = serde_yaml::from_str(document)?;
+            merged = Self::merge_values(merged, value, &mut path);
+        }
+        Ok(serde_yaml::to_string(&merged)?)
+    }
+
+    fn merge_values(base: Value, over: Value, path: &mut Vec<String>) -> Value {
+        let over = match over {
+            Value::Tagged(tagged) if tagged.tag == "override" => return tagged.value,
+            other => other,
+        };
+
+        match (MergeRule::for_path(path), base, over) {
+            (MergeRule::Override, _, over) => over,
+            (MergeRule::KeyValue, base, over) => {
+                Value::Mapping(Self::merge_mappings(Self::key_value_mapping(base), Self::key_value_mapping(over), path))
+            }
+            (MergeRule::NamesOrMapping(_), Value::Sequence(base), Value::Sequence(over)) => Self::union(base, over),
+            (MergeRule::NamesOrMapping(default), base, over) => Value::Mapping(Self::merge_mappings(
+                Self::names_mapping(base, &default),
+                Self::names_mapping(over, &default),
+                path,
+            )),
+            (MergeRule::Keyed(key_of), Value::Sequence(base), Value::Sequence(over)) => Self::merge_keyed(base, over, key_of),
+            (MergeRule::Unique, Value::Sequence(base), Value::Sequence(over)) => Self::union(base, over),
+            (_, Value::Mapping(base), Value::Mapping(over)) => Value::Mapping(Self::merge_mappings(base, over, path)),
+            (_, Value::Sequence(mut base), Value::Sequence(over)) => {
+                base.extend(over);
+                Value::Sequence(base)
+            }
+            (_, _, over) => over,
+        }
+    }
+
+    fn merge_mappings(mut base: Mapping, over: Mapping, path: &mut Vec<String>) -> Mapping {
+        for (key, value) in over {
+            if matches!(&value, Value::Tagged(tagged) if tagged.tag == "reset") {
+                base.shift_remove(&key);
+                continue;
+            }
+            // Merge in place to keep the key order of the first file
+            if let Some(existing) = base.get_mut(&key) {
+                path.push(key.as_str().unwrap_or_default().to_string());
+                *existing = Self::merge_values(std::mem::take(existing), value, path);
+                path.pop();
+            } else {
+                let value = match value {
+                    Value::Tagged(tagged) if tagged.tag == "override" => tagged.value,
+                    value => value,
+                };
+                base.insert(key, value);
+            }
+        }
+        base
+    }
+
+    /// Ajoute les éléments absents de `base`, sans doublon
+    fn union(mut base: Vec<Value>, over: Vec<Value>) -> Value {
+        for item in over {
+            if !base.contains(&item) {
+                base.push(item);
+            }
+        }
+        Value::Sequence(base)
+    }
+
+    /// Remplace les éléments de même clé (cible de montage, nom de secret...) et ajoute les autres
+    fn merge_keyed(mut base: Vec<Value>, over: Vec<Value>, key_of: fn(&Value) -> Option<String>) -> Value {
+        for item in over {
+            let existing = key_of(&item)
+                .and_then(|key| base.iter().position(|b| key_of(b).as_deref() == Some(key.as_str())));
+            match existing {
+                Some(index) => base[index] = item,
+                None => base.push(item),
+            }
+        }
+        Value::Sequence(base)
+    }
+
+    /// Convertit `- KEY=value` en mapping `KEY: value`
+    fn key_value_mapping(value: Value) -> Mapping {
+        match value {
+            Value::Mapping(mapping) => mapping,
+            Value::Sequence(items) => items.into_iter()
+                .filter_map(|item| item.as_str().map(|s| match s.split_once('=') {
+                    Some((key, val)) => (Value::String(key.to_string()), Value::String(val.to_string())),
+                    None => (Value::String(s.to_string()), Value::Null),
+                }))
+                .collect(),
+            _ => Mapping::new(),
+        }
+    }
+
+    /// Convertit `- name` en mapping `name: <default>`
+    fn names_mapping(value: Value, default: &Value) -> Mapping {
+        match value {
+            Value::Mapping(mapping) => mapping,
+            Value::Sequence(items) => items.into_iter().map(|name| (name, default.clone())).collect(),
+            _ => Mapping::new(),
+        }
+    }
+
+    /// Résout les `include` de premier niveau et les `extends` des services d'un fichier compose,
+    /// relativement au dossier `base_dir`, et retourne le contenu autonome ainsi que les fichiers utilisés.
+    /// Les fichiers inclus ou étendus doivent se trouver sous `root` (le repository).
+    /// Un contenu sans `include` ni `extends` est retourné tel quel.
+    pub fn resolve_includes_and_extends(content: &str, base_dir: &Path, root: &Path) -> Result<(String, Vec<PathBuf>)> {
+        let yaml_value: Value = serde_yaml::from_str(content)?;
+        let uses_extends = yaml_value.get("services")
+            .and_then(|s| s.as_mapping())
+            .is_some_and(|services| services.values().any(|service| service.get("extends").is_some()));
+        if yaml_value.get("include").is_none() && !uses_extends {
+            return Ok((content.to_string(), Vec::new()));
+        }
+
+        let mut files = Vec::new();
+        let resolved = Self::resolve_document(yaml_value, base_dir, root, &mut files, &mut Vec::new())?;
+        Ok((serde_yaml::to_string(&resolved)?, files))
+    }
+
+    /// Lit et résout un fichier inclus ou étendu, en détectant les inclusions circulaires
+    fn load_document(path: &Path, root: &Path, files: &mut Vec<PathBuf>, chain: &mut Vec<PathBuf>) -> Result<Value> {
+        let path = Self::inner_path(path, root)?;
+        if chain.contains(&path) {
+            return Err(anyhow::anyhow!("Circular include of {}", path.display()));
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read included file {}: {}", path.display(), e))?;
+        let yaml_value: Value = serde_yaml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))?;
+        if !files.contains(&path) {
+            files.push(path.clone());
+        }
+
+        chain.push(path.clone());
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let resolved = Self::resolve_document(yaml_value, dir, root, files, chain);
+        chain.pop();
+        resolved
+    }
+
+    fn resolve_document(mut document: Value, dir: &Path, root: &Path, files: &mut Vec<PathBuf>, chain: &mut Vec<PathBuf>) -> Result<Value> {
+        // extends first, so that services of included files are already resolved by their own document
+        let service_names: Vec<String> = document.get("services")
+            .and_then(|s| s.as_mapping())
+            .map(|services| services.keys().filter_map(|k| k.as_str().map(|k| k.to_string())).collect())
+            .unwrap_or_default();
+        let mut resolved_services = Vec::new();
+        for name in &service_names {
+            let resolved = Self::resolve_extends(&document, name, dir, root, files, &mut Vec::new())?;
+            resolved_services.push((name.clone(), resolved));
+        }
+        if let Some(services) = document.get_mut("services").and_then(|s| s.as_mapping_mut()) {
+            for (name, service) in resolved_services {
+                services.insert(Value::String(name), service);
+            }
+        }
+
+        let includes = match document.as_mapping_mut().and_then(|m| m.remove("include")) {
+            Some(Value::Sequence(includes)) => includes,
+            Some(Value::Null) | None => return Ok(document),
+            Some(_) => return Err(anyhow::anyhow!("'include' must be a list")),
+        };
+
+        for include in includes {
+            let (paths, project_directory) = match &include {
+                Value::String(path) => (vec![path.clone()], None),
+                Value::Mapping(_) => {
+                    let paths = match include.get("path") {
+                        Some(Value::String(path)) => vec![path.clone()],
+                        Some(Value::Sequence(paths)) => paths.iter().filter_map(|p| p.as_str().map(|p| p.to_string())).collect(),
+                        _ => return Err(anyhow::anyhow!("include entry without 'path'")),
+                    };
+                    (paths, include.get("project_directory").and_then(|d| d.as_str()).map(|d| dir.join(d)))
+                }
+                _ => return Err(anyhow::anyhow!("Invalid include entry")),
+            };
+
+            // Several paths in one entry are merged like several compose files
+            let mut included = Value::Mapping(Mapping::new());
+            let mut first_dir = None;
+            for path in &paths {
+                let path = dir.join(path);
+                let document = Self::load_document(&path, root, files, chain)?;
+                first_dir.get_or_insert_with(|| path.parent().unwrap_or(dir).to_path_buf());
+                included = Self::merge_values(included, document, &mut Vec::new());
+            }
+            let include_dir = project_directory.or(first_dir).unwrap_or_else(|| dir.to_path_buf());
+            Self::rebase_document(&mut included, &include_dir, dir);
+
+            for section in ["services", "volumes", "networks", "secrets", "configs"] {
+                let Some(Value::Mapping(entries)) = included.get(section).cloned() else {
+                    continue;
+                };
+                if document.get(section).is_none_or(|s| s.is_null()) {
+                    document[section] = Value::Mapping(Mapping::new());
+                }
+                let target = document[section].as_mapping_mut()
+                    .ok_or_else(|| anyhow::anyhow!("'{}' must be a mapping", section))?;
+                for (key, value) in entries {
+                    if target.contains_key(&key) {
+                        return Err(anyhow::anyhow!(
+                            "{} '{}' from included file(s) {} conflicts with an existing definition",
+                            section.trim_end_matches('s'), key.as_str().unwrap_or_default(), paths.join(", ")
+                        ));
+                    }
+                    target.insert(key, value);
+                }
+            }
+        }
+
+        Ok(document)
+    }
+
+    /// Résout `extends` pour un service : le service de base (du même fichier ou de `file`) est fusionné
+    /// avec la définition du service selon les règles de fusion de Compose
+    fn resolve_extends(
+        document: &Value,
+        name: &str,
+        dir: &Path,
+        root: &Path,
+        files: &mut Vec<PathBuf>,
+        visiting: &mut Vec<(PathBuf, String)>,
+    ) -> Result<Value> {
+        let service = document.get("services").and_then(|s| s.get(name))
+            .ok_or_else(|| anyhow::anyhow!("Service '{}' to extend not found in {}", name, dir.display()))?;
+        let Some(extends) = service.get("extends") else {
+            return Ok(service.clone());
+        };
+
+        let marker = (dir.to_path_buf(), name.to_string());
+        if visiting.contains(&marker) {
+            return Err(anyhow::anyhow!("Circular extends involving service '{}'", name));
+        }
+        visiting.push(marker);
+
+        let (base_name, base_file) = match extends {
+            Value::String(base_name) => (base_name.clone(), None),
+            Value::Mapping(_) => (
+                extends.get("service").and_then(|s| s.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("'extends' of service '{}' has no 'service'", name))?
+                    .to_string(),
+                extends.get("file").and_then(|f| f.as_str()).map(|f| dir.join(f)),
+            ),
+            _ => return Err(anyhow::anyhow!("Invalid 'extends' in service '{}'", name)),
+        };
+
+        let mut base = match base_file {
+            Some(path) => {
+                let path = Self::inner_path(&path, root)?;
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("Cannot read extended file {}: {}", path.display(), e))?;
+                let base_document: Value = serde_yaml::from_str(&content)
+                    .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))?;
+                if !files.contains(&path) {
+                    files.push(path.clone());
+                }
+                let base_dir = path.parent().unwrap_or(dir).to_path_buf();
+                let mut base = Self::resolve_extends(&base_document, &base_name, &base_dir, root, files, visiting)?;
+                Self::rebase_service(&mut base, &base_dir, dir);
+                base
+            }
+            None => Self::resolve_extends(document, &base_name, dir, root, files, visiting)?,
+        };
+        visiting.pop();
+
+        let mut service = service.clone();
+        if let Some(service) = service.as_mapping_mut() {
+            service.remove("extends");
+        }
+        if let Some(base) = base.as_mapping_mut() {
+            base.remove("extends");
+        }
+        let mut path = vec!["services".to_string(), name.to_string()];
+        Ok(Self::merge_values(base, service, &mut path))
+    }
+
+    /// Réécrit les chemins relatifs d'un document inclus pour qu'ils restent valides depuis `to_dir`
+    fn rebase_document(document: &mut Value, from_dir: &Path, to_dir: &Path) {
+        if let Some(services) = document.get_mut("services").and_then(|s| s.as_mapping_mut()) {
+            for (_name, service) in services {
+                Self::rebase_service(service, from_dir, to_dir);
+            }
+        }
+        for section in ["secrets", "configs"] {
+            if let Some(entries) = document.get_mut(section).and_then(|s| s.as_mapping_mut()) {
+                for (_name, entry) in entries {
+                    if let Some(Value::String(file)) = entry.get_mut("file") {
+                        *file = Self::rebase_path(file, from_dir, to_dir);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Réécrit les sources relatives des montages et les `env_file` d'un service
+    fn rebase_service(service: &mut Value, from_dir: &Path, to_dir: &Path) {
+        if let Some(Value::Sequence(volumes)) = service.get_mut("volumes") {
+            for volume in volumes {
+                match volume {
+                    Value::String(spec) => {
+                        if let Some((source, rest)) = spec.split_once(':') {
+                            if source.starts_with('.') {
+                                *spec = format!("{}:{}", Self::rebase_path(source, from_dir, to_dir), rest);
+                            }
+                        }
+                    }
+                    Value::Mapping(_) => {
+                        if let Some(Value::String(source)) = volume.get_mut("source") {
+                            if source.starts_with('.') {
+                                *source = Self::rebase_path(source, from_dir, to_dir);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        match service.get_mut("env_file") {
+            Some(Value::String(file)) => *file = Self::rebase_path(file, from_dir, to_dir),
+            Some(Value::Sequence(env_files)) => {
+                for env_file in env_files {
+                    match env_file {
+                        Value::String(file) => *file = Self::rebase_path(file, from_dir, to_dir),
+                        Value::Mapping(_) => {
+                            if let Some(Value::String(file)) = env_file.get_mut("path") {
+                                *file = Self::rebase_path(file, from_dir, to_dir);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Exprime un chemin relatif à `from_dir` relativement à `to_dir`
+    fn rebase_path(path: &str, from_dir: &Path, to_dir: &Path) -> String {
+        if Path::new(path).is_absolute() {
+            return path.to_string();
+        }
+        let target = Self::normalize_path(&from_dir.join(path));
+        let base = Self::normalize_path(to_dir);
+
+        let common = target.components().zip(base.components()).take_while(|(a, b)| a == b).count();
+        let mut relative = PathBuf::new();
+        for _ in base.components().skip(common) {
+            relative.push("..");
+        }
+        for component in target.components().skip(common) {
+            relative.push(component);
+        }
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if relative.starts_with("..") {
+            relative
+        } else {
+            format!("./{}", relative)
+        }
+    }
+
+    /// Chemin normalisé d'un fichier inclus ou étendu, qui doit rester sous `root`
+    fn inner_path(path: &Path, root: &Path) -> Result<PathBuf> {
+        let normalized = Self::normalize_path(path);
+        let inside = normalized.strip_prefix(Self::normalize_path(root))
+            .is_ok_and(|relative| relative.components().all(|c| matches!(c, Component::Normal(_))));
+        if !inside {
+            return Err(anyhow::anyhow!("{} is outside the repository", path.display()));
+        }
+        Ok(normalized)
+    }
+
+    /// Normalise un chemin sans accéder au système de fichiers (`a/./b/../c` -> `a/c`)
+    pub fn normalize_path(path: &Path) -> PathBuf {
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        normalized.push("..");
+                    }
+                }
+                other => normalized.push(other),
+            }
+        }
+        normalized
+    }
+
     /// Traite les volumes dans le contenu docker-compose
     pub fn process_volumes(
         compose_content: &str,
@@ -87,47 +517,45 @@ impl ComposeProcessor {
     ) -> Result<()> {
         use crate::models::VolumeType;
 
-        match volumes {
-            serde_yaml::Value::Sequence(seq) => {
-                for volume in seq.iter_mut() {
-                    if let Some(volume_str) = volume.as_str() {
-                        // Check if this is a volume reference (format: volume_id:container_path)
-                        if volume_str.contains(':') {
-                            let parts: Vec<&str> = volume_str.split(':').collect();
-
-                            if parts.len() >= 2 && parts.len() <= 3 {
-                                let volume_id = parts[0];
-                                let container_path = parts[1];
-                                let options = if parts.len() == 3 { parts[2] } else { "" };
-
-                                // Find the volume definition
-                                if let Some(volume_def) = volumes_definitions.iter().find(|v| v.id == volume_id) {
-                                    match volume_def.r#type {
-                                        VolumeType::Volume => {
-                                            // For Docker volumes, use the path as volume name
-                                            let volume_path = if !options.is_empty() {
-                                                format!("{}:{}:{}", volume_def.path, container_path, options)
-                                            } else {
-                                                format!("{}:{}", volume_def.path, container_path)
-                                            };
-                                            *volume = serde_yaml::Value::String(volume_path);
-                                        }
-                                        VolumeType::Binding => {
-                                            // For bindings, replace with NFS path
-                                            let full_nfs_path = Path::new(&nfs_config.path).join(&volume_def.path);
-
-                                            // Create the NFS directory if it doesn't exist
-                                            if !full_nfs_path.exists() {
-                                                fs::create_dir_all(&full_nfs_path)?;
-                                            }
-
-                                            let nfs_path = if !options.is_empty() {
-                                                format!("{}:{}:{}", full_nfs_path.display(), container_path, options)
-                                            } else {
-                                                format!("{}:{}", full_nfs_path.display(), container_path)
-                                            };
-                                            *volume = serde_yaml::Value::String(nfs_path);
+        if let serde_yaml::Value::Sequence(seq) = volumes {
+            for volume in seq.iter_mut() {
+                if let Some(volume_str) = volume.as_str() {
+                    // Check if this is a volume reference (format: volume_id:container_path)
+                    if volume_str.contains(':') {
+                        let parts: Vec<&str> = volume_str.split(':').collect();
+
+                        if parts.len() >= 2 && parts.len() <= 3 {
+                            let volume_id = parts[0];
+                            let container_path = parts[1];
+                            let options = if parts.len() == 3 { parts[2] } else { "" };
+
+                            // Find the volume definition
+                            if let Some(volume_def) = volumes_definitions.iter().find(|v| v.id == volume_id) {
+                                match volume_def.r#type {
+                                    VolumeType::Volume => {
+                                        // For Docker volumes, use the path as volume name
+                                        let volume_path = if !options.is_empty() {
+                                            format!("{}:{}:{}", volume_def.path, container_path, options)
+                                        } else {
+                                            format!("{}:{}", volume_def.path, container_path)
+                                        };
+                                        *volume = serde_yaml::Value::String(volume_path);
+                                    }
+                                    VolumeType::Binding => {
+                                        // For bindings, replace with NFS path
+                                        let full_nfs_path = Path::new(&nfs_config.path).join(&volume_def.path);
+
+                                        // Create the NFS directory if it doesn't exist
+                                        if !full_nfs_path.exists() {
+                                            fs::create_dir_all(&full_nfs_path)?;
                                         }
+
+                                        let nfs_path = if !options.is_empty() {
+                                            format!("{}:{}:{}", full_nfs_path.display(), container_path, options)
+                                        } else {
+                                            format!("{}:{}", full_nfs_path.display(), container_path)
+                                        };
+                                        *volume = serde_yaml::Value::String(nfs_path);
                                     }
                                 }
                             }
@@ -135,7 +563,6 @@ impl ComposeProcessor {
                     }
                 }
             }
-            _ => {}
         }
 
         Ok(())
@@ -270,3 +697,68 @@ impl ComposeProcessor {
     }
 }
 
+
+/// Règle de fusion d'une clé de service, d'après la spécification Compose
+enum MergeRule {
+    /// La valeur du fichier suivant remplace la précédente
+    Override,
+    /// Liste `KEY=value` ou mapping, fusionnés par clé
+    KeyValue,
+    /// Liste de noms ou mapping (valeur par défaut pour les noms seuls)
+    NamesOrMapping(Value),
+    /// Liste dont les éléments sont identifiés par une clé
+    Keyed(fn(&Value) -> Option<String>),
+    /// Liste sans doublons
+    Unique,
+    /// Mappings fusionnés récursivement, listes concaténées, autres valeurs remplacées
+    Default,
+}
+
+impl MergeRule {
+    fn for_path(path: &[String]) -> Self {
+        let key: Vec<&str> = match path {
+            [services, _service, rest @ ..] if services == "services" => rest.iter().map(String::as_str).collect(),
+            _ => return MergeRule::Default,
+        };
+
+        match key.as_slice() {
+            ["command"] | ["entrypoint"] | ["healthcheck", "test"] => MergeRule::Override,
+            ["environment"] | ["labels"] | ["annotations"] | ["sysctls"]
+            | ["deploy", "labels"] | ["build", "args"] | ["build", "labels"] => MergeRule::KeyValue,
+            ["networks"] => MergeRule::NamesOrMapping(Value::Null),
+            ["depends_on"] => {
+                let mut condition = Mapping::new();
+                condition.insert(Value::String("condition".to_string()), Value::String("service_started".to_string()));
+                MergeRule::NamesOrMapping(Value::Mapping(condition))
+            }
+            ["volumes"] => MergeRule::Keyed(volume_target),
+            ["secrets"] | ["configs"] => MergeRule::Keyed(secret_target),
+            ["ports"] | ["expose"] | ["dns"] | ["dns_search"] | ["env_file"] | ["tmpfs"] | ["cap_add"]
+            | ["cap_drop"] | ["security_opt"] | ["extra_hosts"] | ["links"] | ["external_links"] | ["devices"] => MergeRule::Unique,
+            _ => MergeRule::Default,
+        }
+    }
+}
+
+/// Chemin de montage d'un volume (`source:cible[:mode]` ou syntaxe longue)
+fn volume_target(volume: &Value) -> Option<String> {
+    match volume {
+        Value::String(s) => {
+            let parts: Vec<&str> = s.split(':').collect();
+            Some(parts.get(1).unwrap_or(&parts[0]).to_string())
+        }
+        Value::Mapping(_) => volume.get("target").and_then(|t| t.as_str()).map(|t| t.to_string()),
+        _ => None,
+    }
+}
+
+/// Cible d'un secret ou d'une config (nom seul ou syntaxe longue)
+fn secret_target(secret: &Value) -> Option<String> {
+    match secret {
+        Value::String(s) => Some(s.clone()),
+        Value::Mapping(_) => secret.get("target").or_else(|| secret.get("source"))
+            .and_then(|t| t.as_str())
+            .map(|t| t.to_string()),
+        _ => None,
+    }
+}
@@ -1,21 +1,17 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 /// Policy de pull d'images Docker, similaire à k3s
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ImagePullPolicy {
     /// Télécharge toujours l'image depuis le registry (comme k3s Always)
     Always,
     /// Télécharge seulement si l'image n'est pas présente localement (comme k3s IfNotPresent)
+    #[default]
     IfNotPresent,
 }
 
-impl Default for ImagePullPolicy {
-    fn default() -> Self {
-        ImagePullPolicy::IfNotPresent
-    }
-}
-
 impl ImagePullPolicy {
     /// Parse une policy depuis une string
     pub fn from_str(s: &str) -> Result<Self, String> {
@@ -27,35 +23,391 @@ impl ImagePullPolicy {
     }
 }
 
+/// Mode de déploiement des stacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployBackend {
+    /// Stack Docker Swarm (services, réseaux overlay, secrets Swarm)
+    #[default]
+    Swarm,
+    /// Projet Docker Compose sur un Docker Engine sans Swarm (conteneurs, secrets montés depuis des fichiers)
+    Compose,
+}
+
+impl DeployBackend {
+    /// Parse un mode depuis une string
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "swarm" => Ok(DeployBackend::Swarm),
+            "compose" => Ok(DeployBackend::Compose),
+            _ => Err(format!("Unknown deploy backend: {} (expected 'swarm' or 'compose')", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeployBackend::Swarm => "swarm",
+            DeployBackend::Compose => "compose",
+        }
+    }
+}
+
+/// Découverte automatique des stacks d'un repository sans manifeste
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    /// Profondeur maximale des dossiers de stacks (1 = sous-dossiers directs du repository)
+    pub max_depth: usize,
+    /// Motifs glob des dossiers à retenir (tous si vide)
+    pub include: Vec<String>,
+    /// Motifs glob des dossiers à ignorer
+    pub exclude: Vec<String>,
+}
+
+/// Nom du cluster implicite (Docker Engine local) auquel vont les repositories non affectés
+pub const DEFAULT_CLUSTER: &str = "default";
+
+/// Certificats client d'un Docker Engine exposé en TCP avec TLS
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub ca: PathBuf,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// Docker Engine cible (un Swarm, ou un hôte en mode compose) déclaré dans le fichier `DOCKEROPS_CLUSTERS`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    pub name: String,
+    /// `unix:///chemin/docker.sock`, `tcp://hôte:2376` ou `ssh://utilisateur@hôte` (par défaut : socket local)
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Certificats client, pour un hôte `tcp://`
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Clé privée, pour un hôte `ssh://`
+    #[serde(default)]
+    pub ssh_key: Option<PathBuf>,
+    /// Mode de déploiement par défaut des stacks de ce cluster (par défaut : `DOCKEROPS_BACKEND`)
+    #[serde(default)]
+    pub backend: Option<DeployBackend>,
+    /// URLs des repositories déployés sur ce cluster
+    #[serde(default)]
+    pub repositories: Vec<String>,
+}
+
+impl ClusterConfig {
+    /// Cluster implicite : Docker Engine local, via le socket par défaut
+    fn local() -> Self {
+        Self {
+            name: DEFAULT_CLUSTER.to_string(),
+            host: None,
+            tls: None,
+            ssh_key: None,
+            backend: None,
+            repositories: Vec::new(),
+        }
+    }
+}
+
+/// Contenu du fichier `DOCKEROPS_CLUSTERS`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClustersFile {
+    clusters: Vec<ClusterConfig>,
+}
+
+/// Identifiants d'un registry privé déclarés dans le fichier `DOCKEROPS_REGISTRIES`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Hôte du registry (`ghcr.io`, `registry.example.com:5000`, `docker.io`)
+    pub host: String,
+    pub username: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Fichier contenant le mot de passe ou le token, à la place de `password`
+    #[serde(default)]
+    pub password_file: Option<PathBuf>,
+}
+
+/// Contenu du fichier `DOCKEROPS_REGISTRIES`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegistriesFile {
+    registries: Vec<RegistryConfig>,
+}
+
 /// Configuration globale de l'application
 #[derive(Debug, Clone)]
 pub struct Config {
     pub image_pull_policy: ImagePullPolicy,
+    /// Nombre maximal de repositories traités en parallèle
+    pub repo_concurrency: usize,
+    /// Nombre maximal de stacks d'un même repository déployés en parallèle
+    pub stack_concurrency: usize,
+    /// Nombre maximal de pulls d'images simultanés, pour un stack et pour les images référencées
+    pub pull_concurrency: usize,
+    /// Environnement de cette instance (`prod`, `staging`...), qui sélectionne les overlays à appliquer
+    pub environment: Option<String>,
+    /// Variables d'environnement de DockerOps utilisables dans les fichiers compose (`NAME` ou `PREFIX_*`)
+    pub env_allowlist: Vec<String>,
+    /// Découverte des stacks lorsque le repository n'a ni dockerops.yaml ni stacks.yaml (désactivée par défaut)
+    pub discovery: Option<DiscoveryConfig>,
+    /// Mode de déploiement par défaut des stacks (`backend` dans stacks.yaml le remplace pour un stack)
+    pub backend: DeployBackend,
+    /// Dossier des fichiers de secrets montés dans les conteneurs en mode compose (`<dossier>/<secret>`)
+    pub secrets_dir: PathBuf,
+    /// Docker Engines cibles ; contient toujours le cluster `default`
+    pub clusters: Vec<ClusterConfig>,
+    /// Identifiants des registries privés, prioritaires sur ceux du `config.json` de Docker
+    pub registries: Vec<RegistryConfig>,
+    /// `config.json` de Docker (`auths`, `credHelpers`, `credsStore`)
+    pub docker_config: PathBuf,
+    /// Pré-pull des nouvelles images sur tous les nœuds du Swarm avant la mise à jour des services
+    pub prepull: bool,
+    /// Résolution des images en digests au rendu, pour déployer des références `image@sha256:...` (activée par défaut)
+    pub pin_digests: bool,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             image_pull_policy: ImagePullPolicy::default(),
+            repo_concurrency: 2,
+            stack_concurrency: 4,
+            pull_concurrency: 4,
+            environment: None,
+            env_allowlist: Vec::new(),
+            discovery: None,
+            backend: DeployBackend::default(),
+            secrets_dir: PathBuf::from("/etc/dockerops/secrets"),
+            clusters: vec![ClusterConfig::local()],
+            registries: Vec::new(),
+            docker_config: docker_config_path(),
+            prepull: false,
+            pin_digests: true,
         }
     }
 }
 
 impl Config {
+    #[allow(dead_code)]
     pub fn new() -> Self {
         Self::default()
     }
 
     /// Charge la configuration depuis les variables d'environnement
-    pub fn from_env() -> Self {
+    pub fn from_env() -> anyhow::Result<Self> {
         let policy = std::env::var("DOCKEROPS_IMAGE_PULL_POLICY")
             .ok()
             .and_then(|s| ImagePullPolicy::from_str(&s).ok())
             .unwrap_or_default();
 
-        Self {
+        let environment = match std::env::var("DOCKEROPS_ENVIRONMENT") {
+            Ok(name) if !name.trim().is_empty() => Some(parse_environment(name.trim())?),
+            _ => None,
+        };
+
+        let backend = match std::env::var("DOCKEROPS_BACKEND") {
+            Ok(backend) if !backend.trim().is_empty() => DeployBackend::from_str(backend.trim())
+                .map_err(|e| anyhow::anyhow!("Invalid DOCKEROPS_BACKEND: {}", e))?,
+            _ => DeployBackend::default(),
+        };
+
+        let defaults = Self::default();
+        Ok(Self {
             image_pull_policy: policy,
+            repo_concurrency: concurrency_from_env("DOCKEROPS_REPO_CONCURRENCY", defaults.repo_concurrency),
+            stack_concurrency: concurrency_from_env("DOCKEROPS_STACK_CONCURRENCY", defaults.stack_concurrency),
+            pull_concurrency: concurrency_from_env("DOCKEROPS_PULL_CONCURRENCY", defaults.pull_concurrency),
+            environment,
+            env_allowlist: list_from_env("DOCKEROPS_ENV_ALLOWLIST"),
+            discovery: discovery_from_env()?,
+            backend,
+            secrets_dir: std::env::var("DOCKEROPS_SECRETS_DIR").map(PathBuf::from).unwrap_or(defaults.secrets_dir),
+            clusters: clusters_from_env()?,
+            registries: registries_from_env()?,
+            docker_config: defaults.docker_config,
+            prepull: flag_from_env("DOCKEROPS_PREPULL"),
+            pin_digests: enabled_unless_env("DOCKEROPS_PIN_DIGESTS"),
+        })
+    }
+
+    /// Retrouve un cluster par son nom
+    pub fn cluster(&self, name: &str) -> Option<&ClusterConfig> {
+        self.clusters.iter().find(|c| c.name == name)
+    }
+
+    /// Clusters auxquels un repository est affecté (`default` s'il n'est affecté à aucun)
+    pub fn repository_clusters(&self, repository_url: &str) -> Vec<&str> {
+        let clusters: Vec<&str> = self.clusters.iter()
+            .filter(|c| c.repositories.iter().any(|url| same_repository(url, repository_url)))
+            .map(|c| c.name.as_str())
+            .collect();
+        if clusters.is_empty() {
+            vec![DEFAULT_CLUSTER]
+        } else {
+            clusters
         }
     }
+
+    /// Mode de déploiement par défaut d'un cluster
+    pub fn cluster_backend(&self, name: &str) -> DeployBackend {
+        self.cluster(name).and_then(|c| c.backend).unwrap_or(self.backend)
+    }
+}
+
+/// Compare deux URLs de repository, sans tenir compte d'un `/` ou `.git` final
+fn same_repository(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| url.trim().trim_end_matches('/').trim_end_matches(".git").to_string();
+    normalize(a) == normalize(b)
+}
+
+/// Charge les clusters depuis le fichier YAML désigné par `DOCKEROPS_CLUSTERS` ; le cluster `default`
+/// (socket local) est ajouté s'il n'y est pas déclaré
+fn clusters_from_env() -> anyhow::Result<Vec<ClusterConfig>> {
+    let mut clusters = match std::env::var("DOCKEROPS_CLUSTERS") {
+        Ok(path) if !path.trim().is_empty() => {
+            let path = path.trim();
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Cannot read DOCKEROPS_CLUSTERS file '{}': {}", path, e))?;
+            let file: ClustersFile = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid DOCKEROPS_CLUSTERS file '{}': {}", path, e))?;
+            file.clusters
+        }
+        _ => Vec::new(),
+    };
+
+    let mut names = std::collections::HashSet::new();
+    for cluster in &clusters {
+        if cluster.name.is_empty() || !cluster.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(anyhow::anyhow!("Invalid cluster name '{}': only letters, digits, '-' and '_' are allowed", cluster.name));
+        }
+        if !names.insert(cluster.name.as_str()) {
+            return Err(anyhow::anyhow!("Cluster '{}' is declared several times", cluster.name));
+        }
+        let scheme = cluster.host.as_deref().and_then(|host| host.split_once("://")).map(|(scheme, _)| scheme);
+        if cluster.tls.is_some() && !matches!(scheme, Some("tcp" | "https")) {
+            return Err(anyhow::anyhow!("Cluster '{}': tls requires a tcp:// host", cluster.name));
+        }
+        if cluster.ssh_key.is_some() && scheme != Some("ssh") {
+            return Err(anyhow::anyhow!("Cluster '{}': ssh_key requires an ssh:// host", cluster.name));
+        }
+    }
+
+    if !names.contains(DEFAULT_CLUSTER) {
+        clusters.insert(0, ClusterConfig::local());
+    }
+    Ok(clusters)
+}
+
+/// Charge les identifiants des registries depuis le fichier YAML désigné par `DOCKEROPS_REGISTRIES`
+fn registries_from_env() -> anyhow::Result<Vec<RegistryConfig>> {
+    let path = match std::env::var("DOCKEROPS_REGISTRIES") {
+        Ok(path) if !path.trim().is_empty() => path.trim().to_string(),
+        _ => return Ok(Vec::new()),
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Cannot read DOCKEROPS_REGISTRIES file '{}': {}", path, e))?;
+    let file: RegistriesFile = serde_yaml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Invalid DOCKEROPS_REGISTRIES file '{}': {}", path, e))?;
+
+    for registry in &file.registries {
+        if registry.password.is_some() == registry.password_file.is_some() {
+            return Err(anyhow::anyhow!("Registry '{}': set either password or password_file", registry.host));
+        }
+    }
+    Ok(file.registries)
+}
+
+/// Chemin du `config.json` de Docker : `$DOCKER_CONFIG/config.json`, sinon `~/.docker/config.json`
+fn docker_config_path() -> PathBuf {
+    let dir = std::env::var_os("DOCKER_CONFIG").map(PathBuf::from).unwrap_or_else(|| {
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+        home.join(".docker")
+    });
+    dir.join("config.json")
 }
 
+/// Lit une option activée par `1`, `true` ou `yes` depuis une variable d'environnement
+fn flag_from_env(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Lit une option activée par défaut, désactivée par `0`, `false` ou `no` dans une variable d'environnement
+fn enabled_unless_env(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| !matches!(value.trim().to_lowercase().as_str(), "0" | "false" | "no"))
+        .unwrap_or(true)
+}
+
+/// Lit une liste séparée par `,` ou `;` depuis une variable d'environnement
+fn list_from_env(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .map(|list| list.split([',', ';']).map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Lit la configuration de découverte des stacks (`DOCKEROPS_DISCOVERY=true` pour l'activer)
+fn discovery_from_env() -> anyhow::Result<Option<DiscoveryConfig>> {
+    if !flag_from_env("DOCKEROPS_DISCOVERY") {
+        return Ok(None);
+    }
+
+    let max_depth = match std::env::var("DOCKEROPS_DISCOVERY_DEPTH") {
+        Ok(depth) => depth.trim().parse::<usize>().ok().filter(|d| *d >= 1)
+            .ok_or_else(|| anyhow::anyhow!("Invalid DOCKEROPS_DISCOVERY_DEPTH '{}': expected a number >= 1", depth))?,
+        Err(_) => 1,
+    };
+
+    let mut patterns = Vec::new();
+    for name in ["DOCKEROPS_DISCOVERY_INCLUDE", "DOCKEROPS_DISCOVERY_EXCLUDE"] {
+        let list = list_from_env(name);
+        for pattern in &list {
+            globset::Glob::new(pattern).map_err(|e| anyhow::anyhow!("Invalid {} pattern '{}': {}", name, pattern, e))?;
+        }
+        patterns.push(list);
+    }
+    let exclude = patterns.pop().unwrap_or_default();
+    let include = patterns.pop().unwrap_or_default();
+
+    Ok(Some(DiscoveryConfig { max_depth, include, exclude }))
+}
+
+
+/// Lit une limite de concurrence (au moins 1) depuis une variable d'environnement
+fn concurrency_from_env(name: &str, default: usize) -> usize {
+    std::env::var(name)
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .map(|n| n.max(1))
+        .unwrap_or(default)
+}
+
+/// Valide un nom d'environnement, utilisé tel quel dans les chemins des overlays
+fn parse_environment(name: &str) -> anyhow::Result<String> {
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(anyhow::anyhow!("Invalid DOCKEROPS_ENVIRONMENT '{}': only letters, digits, '-' and '_' are allowed", name));
+    }
+    Ok(name.to_string())
+}
+
+/// Parse une durée de la forme `90`, `90s`, `15m`, `2h` ou `1d`
+pub fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: u64 = value.parse().map_err(|_| format!("Invalid duration: {}", s))?;
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(format!("Unknown duration unit '{}' in: {}", unit, s)),
+    };
+    Ok(std::time::Duration::from_secs(value * multiplier))
+}
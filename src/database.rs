@@ -1,5 +1,6 @@
 use sqlx::sqlite::SqlitePool;
-use crate::models::{Image, Stack, RepositoryCache};
+use sqlx::Row;
+use crate::models::{Image, Stack, RepositoryCache, Deployment, Suspension};
 
 pub struct Database {
     pool: SqlitePool,
@@ -45,13 +46,20 @@ impl Database {
                 compose_path TEXT NOT NULL,
                 hash TEXT NOT NULL,
                 status TEXT NOT NULL DEFAULT 'stopped',
-                UNIQUE(name, repository_url)
+                cluster TEXT NOT NULL DEFAULT 'default',
+                UNIQUE(name, repository_url, cluster)
             )
             "#,
         )
         .execute(pool)
         .await?;
 
+        Self::add_column_if_missing(pool, "stacks", "pinned", "INTEGER NOT NULL DEFAULT 0").await?;
+        Self::add_column_if_missing(pool, "stacks", "depends_on", "TEXT NOT NULL DEFAULT ''").await?;
+        Self::add_column_if_missing(pool, "stacks", "service_digests", "TEXT NOT NULL DEFAULT '{}'").await?;
+        Self::add_column_if_missing(pool, "stacks", "backend", "TEXT NOT NULL DEFAULT 'swarm'").await?;
+        Self::add_stacks_cluster(pool).await?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS repository_cache (
@@ -64,58 +72,134 @@ impl Database {
         .execute(pool)
         .await?;
 
-        Ok(())
-    }
-
-    // Image operations
-    pub async fn create_image(&self, image: &Image) -> Result<i64, sqlx::Error> {
-        let id = sqlx::query(
-            "INSERT OR IGNORE INTO images (name, reference_count) VALUES (?, ?)"
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS deployments (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                stack_name TEXT NOT NULL,
+                repository_url TEXT NOT NULL,
+                commit_sha TEXT,
+                rendered_compose TEXT NOT NULL,
+                image_digests TEXT NOT NULL DEFAULT '{}',
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                status TEXT NOT NULL DEFAULT 'running',
+                error TEXT
+            )
+            "#,
         )
-        .bind(&image.name)
-        .bind(image.reference_count)
-        .execute(&self.pool)
-        .await?
-        .last_insert_rowid();
+        .execute(pool)
+        .await?;
 
-        Ok(id)
-    }
+        Self::add_column_if_missing(pool, "deployments", "cluster", "TEXT NOT NULL DEFAULT 'default'").await?;
+        Self::add_column_if_missing(pool, "deployments", "resolved_images", "TEXT NOT NULL DEFAULT '[]'").await?;
 
-    pub async fn get_image_by_name(&self, name: &str) -> Result<Option<Image>, sqlx::Error> {
-        let row = sqlx::query_as::<_, Image>(
-            "SELECT id, name, reference_count FROM images WHERE name = ?"
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS suspensions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                target TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                reason TEXT,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                UNIQUE(target, kind)
+            )
+            "#,
         )
-        .bind(name)
-        .fetch_optional(&self.pool)
+        .execute(pool)
         .await?;
 
-        Ok(row)
+        Ok(())
     }
 
-    pub async fn update_image_reference_count(&self, name: &str, count: i32) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE images SET reference_count = ? WHERE name = ?")
-            .bind(count)
-            .bind(name)
-            .execute(&self.pool)
-            .await?;
+    /// Indique si une table possède une colonne
+    async fn has_column(pool: &SqlitePool, table: &str, column: &str) -> Result<bool, sqlx::Error> {
+        Ok(sqlx::query("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .any(|row| row.get::<String, _>("name") == column))
+    }
+
+    /// Ajoute une colonne à une table existante si elle n'existe pas encore (bases créées par une version antérieure)
+    async fn add_column_if_missing(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<(), sqlx::Error> {
+        if !Self::has_column(pool, table, column).await? {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+        }
 
         Ok(())
     }
 
+    /// Ajoute le cluster à la table stacks et à sa clé unique ; SQLite ne pouvant pas modifier une contrainte,
+    /// la table est reconstruite (les stacks existants sont affectés au cluster `default`)
+    async fn add_stacks_cluster(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+        if Self::has_column(pool, "stacks", "cluster").await? {
+            return Ok(());
+        }
 
+        let columns = "id, name, repository_url, compose_path, hash, status, pinned, depends_on, service_digests, backend";
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE stacks_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                repository_url TEXT NOT NULL,
+                compose_path TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'stopped',
+                pinned INTEGER NOT NULL DEFAULT 0,
+                depends_on TEXT NOT NULL DEFAULT '',
+                service_digests TEXT NOT NULL DEFAULT '{}',
+                backend TEXT NOT NULL DEFAULT 'swarm',
+                cluster TEXT NOT NULL DEFAULT 'default',
+                UNIQUE(name, repository_url, cluster)
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!("INSERT INTO stacks_new ({0}) SELECT {0} FROM stacks", columns))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DROP TABLE stacks").execute(&mut *tx).await?;
+        sqlx::query("ALTER TABLE stacks_new RENAME TO stacks").execute(&mut *tx).await?;
+        tx.commit().await
+    }
 
+    // Image operations
+    /// Incrémente le compteur de références d'une image, en la créant si besoin (atomique)
+    pub async fn increment_image_reference(&self, name: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO images (name, reference_count) VALUES (?, 1) \
+             ON CONFLICT(name) DO UPDATE SET reference_count = reference_count + 1"
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await?;
 
+        Ok(())
+    }
 
     // Stack operations
     pub async fn create_stack(&self, stack: &Stack) -> Result<i64, sqlx::Error> {
         let id = sqlx::query(
-            "INSERT OR REPLACE INTO stacks (name, repository_url, compose_path, hash, status) VALUES (?, ?, ?, ?, ?)"
+            "INSERT OR REPLACE INTO stacks (name, repository_url, compose_path, hash, status, pinned, depends_on, service_digests, backend, cluster) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )
         .bind(&stack.name)
         .bind(&stack.repository_url)
         .bind(&stack.compose_path)
         .bind(&stack.hash)
         .bind(&stack.status)
+        .bind(stack.pinned)
+        .bind(&stack.depends_on)
+        .bind(&stack.service_digests)
+        .bind(&stack.backend)
+        .bind(&stack.cluster)
         .execute(&self.pool)
         .await?
         .last_insert_rowid();
@@ -123,21 +207,33 @@ impl Database {
         Ok(id)
     }
 
-    pub async fn get_stack_by_name(&self, name: &str, repository_url: &str) -> Result<Option<Stack>, sqlx::Error> {
+    pub async fn get_stack_by_name(&self, name: &str, repository_url: &str, cluster: &str) -> Result<Option<Stack>, sqlx::Error> {
         let row = sqlx::query_as::<_, Stack>(
-            "SELECT id, name, repository_url, compose_path, hash, status FROM stacks WHERE name = ? AND repository_url = ?"
+            "SELECT id, name, repository_url, compose_path, hash, status, pinned, depends_on, service_digests, backend, cluster FROM stacks WHERE name = ? AND repository_url = ? AND cluster = ?"
         )
         .bind(name)
         .bind(repository_url)
+        .bind(cluster)
         .fetch_optional(&self.pool)
         .await?;
 
         Ok(row)
     }
 
+    pub async fn get_stacks_by_name(&self, name: &str) -> Result<Vec<Stack>, sqlx::Error> {
+        let stacks = sqlx::query_as::<_, Stack>(
+            "SELECT id, name, repository_url, compose_path, hash, status, pinned, depends_on, service_digests, backend, cluster FROM stacks WHERE name = ? ORDER BY repository_url, cluster"
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stacks)
+    }
+
     pub async fn get_all_stacks(&self) -> Result<Vec<Stack>, sqlx::Error> {
         let stacks = sqlx::query_as::<_, Stack>(
-            "SELECT id, name, repository_url, compose_path, hash, status FROM stacks ORDER BY name"
+            "SELECT id, name, repository_url, compose_path, hash, status, pinned, depends_on, service_digests, backend, cluster FROM stacks ORDER BY name, cluster"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -145,22 +241,72 @@ impl Database {
         Ok(stacks)
     }
 
-    pub async fn update_stack_status(&self, name: &str, repository_url: &str, status: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE stacks SET status = ? WHERE name = ? AND repository_url = ?")
+    pub async fn update_stack_status(&self, name: &str, repository_url: &str, cluster: &str, status: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET status = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
             .bind(status)
             .bind(name)
             .bind(repository_url)
+            .bind(cluster)
             .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn update_stack_hash(&self, name: &str, repository_url: &str, hash: &str) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE stacks SET hash = ? WHERE name = ? AND repository_url = ?")
+    pub async fn update_stack_hash(&self, name: &str, repository_url: &str, cluster: &str, hash: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET hash = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
             .bind(hash)
             .bind(name)
             .bind(repository_url)
+            .bind(cluster)
+            .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_stack_service_digests(&self, name: &str, repository_url: &str, cluster: &str, service_digests: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET service_digests = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
+            .bind(service_digests)
+            .bind(name)
+            .bind(repository_url)
+            .bind(cluster)
+            .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_stack_dependencies(&self, name: &str, repository_url: &str, cluster: &str, depends_on: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET depends_on = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
+            .bind(depends_on)
+            .bind(name)
+            .bind(repository_url)
+            .bind(cluster)
+            .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_stack_backend(&self, name: &str, repository_url: &str, cluster: &str, backend: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET backend = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
+            .bind(backend)
+            .bind(name)
+            .bind(repository_url)
+            .bind(cluster)
+            .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_stack_pinned(&self, name: &str, repository_url: &str, cluster: &str, pinned: bool) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE stacks SET pinned = ? WHERE name = ? AND repository_url = ? AND cluster = ?")
+            .bind(pinned)
+            .bind(name)
+            .bind(repository_url)
+            .bind(cluster)
             .execute(&self.pool)
         .await?;
 
@@ -250,4 +396,153 @@ impl Database {
 
         Ok(images)
     }
-}
\ No newline at end of file
+
+    // Deployment history operations
+    pub async fn create_deployment(&self, deployment: &Deployment) -> Result<i64, sqlx::Error> {
+        let id = sqlx::query(
+            "INSERT INTO deployments (stack_name, repository_url, commit_sha, rendered_compose, image_digests, resolved_images, started_at, finished_at, status, error, cluster) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&deployment.stack_name)
+        .bind(&deployment.repository_url)
+        .bind(&deployment.commit_sha)
+        .bind(&deployment.rendered_compose)
+        .bind(&deployment.image_digests)
+        .bind(&deployment.resolved_images)
+        .bind(&deployment.started_at)
+        .bind(&deployment.finished_at)
+        .bind(&deployment.status)
+        .bind(&deployment.error)
+        .bind(&deployment.cluster)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    pub async fn finish_deployment(&self, id: i64, status: &str, image_digests: &str, error: Option<&str>) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query("UPDATE deployments SET status = ?, image_digests = ?, error = ?, finished_at = ? WHERE id = ?")
+            .bind(status)
+            .bind(image_digests)
+            .bind(error)
+            .bind(&now)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_deployment(&self, id: i64) -> Result<Option<Deployment>, sqlx::Error> {
+        let row = sqlx::query_as::<_, Deployment>(
+            "SELECT id, stack_name, repository_url, commit_sha, rendered_compose, image_digests, resolved_images, started_at, finished_at, status, error, cluster FROM deployments WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Liste les déploiements réussis d'un stack, du plus récent au plus ancien
+    pub async fn get_successful_deployments(&self, stack_name: &str, repository_url: &str, cluster: &str) -> Result<Vec<Deployment>, sqlx::Error> {
+        let deployments = sqlx::query_as::<_, Deployment>(
+            "SELECT id, stack_name, repository_url, commit_sha, rendered_compose, image_digests, resolved_images, started_at, finished_at, status, error, cluster FROM deployments \
+             WHERE stack_name = ? AND repository_url = ? AND cluster = ? AND status = 'succeeded' ORDER BY id DESC"
+        )
+        .bind(stack_name)
+        .bind(repository_url)
+        .bind(cluster)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deployments)
+    }
+
+    /// Liste les déploiements du plus récent au plus ancien, filtrés par stack, repository et/ou cluster
+    pub async fn get_deployments(&self, stack_name: Option<&str>, repository_url: Option<&str>, cluster: Option<&str>, limit: i64) -> Result<Vec<Deployment>, sqlx::Error> {
+        let deployments = sqlx::query_as::<_, Deployment>(
+            "SELECT id, stack_name, repository_url, commit_sha, rendered_compose, image_digests, resolved_images, started_at, finished_at, status, error, cluster FROM deployments \
+             WHERE (?1 IS NULL OR stack_name = ?1) AND (?2 IS NULL OR repository_url = ?2) AND (?3 IS NULL OR cluster = ?3) \
+             ORDER BY id DESC LIMIT ?4"
+        )
+        .bind(stack_name)
+        .bind(repository_url)
+        .bind(cluster)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deployments)
+    }
+
+    // Suspension operations
+    pub async fn suspend(&self, target: &str, kind: &str, reason: Option<&str>, expires_at: Option<&str>) -> Result<(), sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query(
+            "INSERT OR REPLACE INTO suspensions (target, kind, reason, created_at, expires_at) VALUES (?, ?, ?, ?, ?)"
+        )
+        .bind(target)
+        .bind(kind)
+        .bind(reason)
+        .bind(&now)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Supprime la suspension d'une cible (stack ou repository) ; retourne false si elle n'était pas suspendue
+    pub async fn resume(&self, target: &str, kind: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM suspensions WHERE target = ? AND kind = ?")
+            .bind(target)
+            .bind(kind)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Supprime les suspensions expirées ; retourne le nombre de suspensions supprimées
+    pub async fn delete_expired_suspensions(&self) -> Result<u64, sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let result = sqlx::query("DELETE FROM suspensions WHERE expires_at IS NOT NULL AND expires_at <= ?")
+            .bind(&now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Retourne la suspension active (non expirée) qui s'applique à un stack, directement ou via son repository
+    pub async fn get_active_suspension(&self, stack_name: &str, repository_url: &str) -> Result<Option<Suspension>, sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let row = sqlx::query_as::<_, Suspension>(
+            "SELECT id, target, kind, reason, created_at, expires_at FROM suspensions \
+             WHERE ((kind = 'stack' AND target = ?) OR (kind = 'repository' AND target = ?)) \
+             AND (expires_at IS NULL OR expires_at > ?) ORDER BY kind LIMIT 1"
+        )
+        .bind(stack_name)
+        .bind(repository_url)
+        .bind(&now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    pub async fn get_active_suspensions(&self) -> Result<Vec<Suspension>, sqlx::Error> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let suspensions = sqlx::query_as::<_, Suspension>(
+            "SELECT id, target, kind, reason, created_at, expires_at FROM suspensions \
+             WHERE expires_at IS NULL OR expires_at > ? ORDER BY target"
+        )
+        .bind(&now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(suspensions)
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::models::StackDefinition;
+
+/// Graphe des dépendances entre stacks (arête : stack -> stack dont il dépend)
+pub struct DependencyGraph {
+    /// Nom du stack -> noms des stacks dont il dépend, dans l'ordre de déclaration
+    nodes: BTreeMap<String, Vec<String>>,
+    /// Ordre de déclaration des stacks, pour un résultat stable
+    order: Vec<String>,
+    /// Arêtes soumises à une attente de santé
+    healthy_edges: BTreeSet<(String, String)>,
+}
+
+impl DependencyGraph {
+    /// Construit le graphe à partir de paires (stack, dépendances) et vérifie que chaque dépendance existe
+    pub fn new(nodes: Vec<(String, Vec<String>)>) -> Result<Self> {
+        let order: Vec<String> = nodes.iter().map(|(name, _)| name.clone()).collect();
+        let nodes: BTreeMap<String, Vec<String>> = nodes.into_iter().collect();
+
+        for (name, deps) in &nodes {
+            for dep in deps {
+                if !nodes.contains_key(dep) {
+                    return Err(anyhow::anyhow!("Stack '{}' depends on unknown stack '{}'", name, dep));
+                }
+            }
+        }
+
+        Ok(Self { nodes, order, healthy_edges: BTreeSet::new() })
+    }
+
+    /// Construit le graphe depuis les définitions de stacks.yaml
+    pub fn from_definitions(definitions: &[StackDefinition]) -> Result<Self> {
+        let mut graph = Self::new(
+            definitions.iter()
+                .map(|d| (d.name.clone(), d.depends_on.iter().map(|dep| dep.name().to_string()).collect()))
+                .collect(),
+        )?;
+        for definition in definitions {
+            for dep in definition.depends_on.iter().filter(|dep| dep.wait_healthy()) {
+                graph.healthy_edges.insert((definition.name.clone(), dep.name().to_string()));
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Découpe les stacks en vagues successives (tri topologique) :
+    /// chaque stack n'apparaît qu'après tous ceux dont il dépend. Échoue en cas de cycle.
+    pub fn levels(&self) -> Result<Vec<Vec<String>>> {
+        let mut remaining: Vec<&String> = self.order.iter().collect();
+        let mut placed: BTreeSet<&str> = BTreeSet::new();
+        let mut levels = Vec::new();
+
+        while !remaining.is_empty() {
+            let (ready, blocked): (Vec<&String>, Vec<&String>) = remaining.into_iter()
+                .partition(|name| self.nodes[*name].iter().all(|dep| placed.contains(dep.as_str())));
+
+            if ready.is_empty() {
+                let cycle: Vec<&str> = blocked.iter().map(|s| s.as_str()).collect();
+                return Err(anyhow::anyhow!("Dependency cycle detected between stacks: {}", cycle.join(", ")));
+            }
+
+            placed.extend(ready.iter().map(|s| s.as_str()));
+            levels.push(ready.into_iter().cloned().collect());
+            remaining = blocked;
+        }
+
+        Ok(levels)
+    }
+
+    /// Ordre de suppression : l'inverse de l'ordre de déploiement
+    pub fn removal_order(&self) -> Result<Vec<String>> {
+        Ok(self.levels()?.into_iter().rev().flatten().collect())
+    }
+
+    /// Rend le graphe au format DOT (Graphviz)
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph stacks {\n    rankdir=LR;\n    node [shape=box];\n");
+        for name in &self.order {
+            dot.push_str(&format!("    \"{}\";\n", name));
+        }
+        for name in &self.order {
+            for dep in &self.nodes[name] {
+                if self.healthy_edges.contains(&(name.clone(), dep.clone())) {
+                    dot.push_str(&format!("    \"{}\" -> \"{}\" [label=\"healthy\"];\n", name, dep));
+                } else {
+                    dot.push_str(&format!("    \"{}\" -> \"{}\";\n", name, dep));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
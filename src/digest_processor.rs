@@ -0,0 +1,142 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_yaml::Value;
+use walkdir::WalkDir;
+use crate::compose_processor::ComposeProcessor;
+
+/// Processeur pour les empreintes SHA-256 des entrées d'un stack (compose, fichiers montés, env files...)
+pub struct DigestProcessor;
+
+impl DigestProcessor {
+    /// Empreinte SHA-256 hexadécimale d'un contenu
+    pub fn sha256_hex(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    /// Empreinte d'un stack : compose rendu, fichiers inclus et empreintes des entrées de chaque service
+    pub fn stack_digest(
+        compose_content: &str,
+        included_files: &[PathBuf],
+        service_digests: &BTreeMap<String, String>,
+    ) -> Result<String> {
+        let mut hasher = Sha256::new();
+        hasher.update(compose_content.as_bytes());
+        for path in included_files {
+            hasher.update(fs::read(path)?);
+        }
+        for (service, digest) in service_digests {
+            hasher.update(format!("\0{}={}", service, digest).as_bytes());
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Empreinte, pour chaque service, des fichiers qu'il référence : sources des montages bind,
+    /// `env_file`, fichiers des `secrets` et `configs`. Les chemins relatifs sont résolus depuis
+    /// `compose_dir` ; seuls les chemins situés sous l'une des `roots` (repository, NFS) sont lus.
+    pub fn service_digests(compose_content: &str, compose_dir: &Path, roots: &[&Path]) -> Result<BTreeMap<String, String>> {
+        let yaml_value: Value = serde_yaml::from_str(compose_content)?;
+        let mut digests = BTreeMap::new();
+
+        let Some(services) = yaml_value.get("services").and_then(|s| s.as_mapping()) else {
+            return Ok(digests);
+        };
+
+        for (name, service) in services {
+            let Some(name) = name.as_str() else {
+                continue;
+            };
+
+            let mut hasher = Sha256::new();
+            for reference in Self::service_files(&yaml_value, service) {
+                let path = ComposeProcessor::normalize_path(&compose_dir.join(&reference));
+                if !roots.iter().any(|root| path.starts_with(root)) {
+                    continue;
+                }
+                hasher.update(format!("\0{}\0", reference).as_bytes());
+                Self::hash_tree(&path, &mut hasher)?;
+            }
+            digests.insert(name.to_string(), format!("{:x}", hasher.finalize()));
+        }
+
+        Ok(digests)
+    }
+
+    /// Chemins de fichiers ou dossiers référencés par un service
+    fn service_files(document: &Value, service: &Value) -> BTreeSet<String> {
+        let mut files = BTreeSet::new();
+
+        if let Some(volumes) = service.get("volumes").and_then(|v| v.as_sequence()) {
+            for volume in volumes {
+                let source = match volume {
+                    Value::String(spec) => spec.split_once(':').map(|(source, _)| source),
+                    Value::Mapping(_) if volume.get("type").and_then(|t| t.as_str()) == Some("bind") => {
+                        volume.get("source").and_then(|s| s.as_str())
+                    }
+                    _ => None,
+                };
+                // Named volumes have no path separator
+                if let Some(source) = source.filter(|s| s.starts_with('.') || s.starts_with('/')) {
+                    files.insert(source.to_string());
+                }
+            }
+        }
+
+        match service.get("env_file") {
+            Some(Value::String(file)) => {
+                files.insert(file.clone());
+            }
+            Some(Value::Sequence(env_files)) => {
+                for env_file in env_files {
+                    let file = env_file.as_str().or_else(|| env_file.get("path").and_then(|p| p.as_str()));
+                    if let Some(file) = file {
+                        files.insert(file.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for section in ["secrets", "configs"] {
+            let Some(references) = service.get(section).and_then(|s| s.as_sequence()) else {
+                continue;
+            };
+            for reference in references {
+                let name = reference.as_str().or_else(|| reference.get("source").and_then(|s| s.as_str()));
+                let file = name
+                    .and_then(|name| document.get(section).and_then(|s| s.get(name)))
+                    .and_then(|definition| definition.get("file"))
+                    .and_then(|file| file.as_str());
+                if let Some(file) = file {
+                    files.insert(file.to_string());
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Ajoute au hasher le contenu d'un fichier, ou de tous les fichiers d'un dossier dans un ordre stable
+    fn hash_tree(path: &Path, hasher: &mut Sha256) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        for entry in WalkDir::new(path).follow_links(false).sort_by_file_name() {
+            let entry = entry?;
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            let file_type = entry.file_type();
+            if file_type.is_file() {
+                hasher.update(format!("\0{}\0", relative.display()).as_bytes());
+                hasher.update(fs::read(entry.path())?);
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(entry.path())?;
+                hasher.update(format!("\0{}->{}\0", relative.display(), target.display()).as_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
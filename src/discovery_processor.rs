@@ -0,0 +1,90 @@
+use anyhow::Result;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+use walkdir::WalkDir;
+use crate::config::DiscoveryConfig;
+use crate::models::StackDefinition;
+use crate::stack_processor::COMPOSE_FILE_NAMES;
+use crate::template_processor::TemplateProcessor;
+
+/// Processeur pour la découverte des stacks d'un repository sans manifeste : chaque dossier contenant
+/// un fichier compose est un stack, nommé d'après son chemin (`apps/web` -> `apps-web`)
+pub struct DiscoveryProcessor;
+
+impl DiscoveryProcessor {
+    /// Stacks découverts sous `repo_path`, triés par chemin. Les dossiers cachés et `overlays` sont ignorés,
+    /// ainsi que les sous-dossiers d'un stack découvert.
+    pub fn discover(repo_path: &Path, config: &DiscoveryConfig) -> Result<Vec<StackDefinition>> {
+        let include = Self::glob_set(&config.include)?;
+        let exclude = Self::glob_set(&config.exclude)?;
+
+        let mut stacks = Vec::new();
+        let mut walker = WalkDir::new(repo_path)
+            .min_depth(1)
+            .max_depth(config.max_depth)
+            .sort_by_file_name()
+            .into_iter();
+
+        while let Some(entry) = walker.next() {
+            let entry = entry?;
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let file_name = entry.file_name().to_string_lossy();
+            if file_name.starts_with('.') || file_name == "overlays" {
+                walker.skip_current_dir();
+                continue;
+            }
+
+            let has_compose_file = COMPOSE_FILE_NAMES.iter()
+                .any(|name| TemplateProcessor::existing_file(&entry.path().join(name)).is_some());
+            if !has_compose_file {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(repo_path)?;
+            let relative = relative.components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+            // Nested directories of a stack (configs, overlays...) are never stacks themselves
+            walker.skip_current_dir();
+
+            if (!config.include.is_empty() && !include.is_match(&relative)) || exclude.is_match(&relative) {
+                continue;
+            }
+
+            let name = Self::stack_name(&relative);
+            stacks.push(StackDefinition {
+                path: (name != relative).then_some(relative),
+                name,
+                compose_files: Vec::new(),
+                enabled: true,
+                stack_name: None,
+                secrets: None,
+                depends_on: Vec::new(),
+                backend: None,
+                cluster: None,
+            });
+        }
+
+        Ok(stacks)
+    }
+
+    /// Nom de stack dérivé d'un chemin relatif : minuscules, `/` et caractères non autorisés remplacés par `-`
+    fn stack_name(relative: &str) -> String {
+        relative.to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '-' })
+            .collect()
+    }
+
+    /// `*` ne traverse pas les `/` : `apps/*` ne retient que les sous-dossiers directs de `apps`
+    fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(GlobBuilder::new(pattern).literal_separator(true).build()?);
+        }
+        Ok(builder.build()?)
+    }
+}
@@ -1,18 +1,88 @@
 use anyhow::Result;
-use bollard::Docker;
+use bollard::{Docker, API_DEFAULT_VERSION};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use crate::config::ClusterConfig;
+
+/// Délai des requêtes vers le Docker Engine, en secondes (valeur par défaut de bollard)
+const CONNECT_TIMEOUT: u64 = 120;
+
+/// Socket Docker par défaut, et socket de l'API compatible de Podman (service `podman.socket`)
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+/// Moteur qui expose l'API Docker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    Docker,
+    /// Podman via son API compatible Docker : pas de Swarm, images nommées `docker.io/...` ou `localhost/...`
+    Podman,
+}
 
 /// Client Docker utilisant bollard
+#[derive(Clone)]
 pub struct DockerClient {
     docker: Docker,
+    /// Moteur détecté à la première requête qui en dépend
+    engine: Arc<OnceCell<Engine>>,
 }
 
 impl DockerClient {
-    /// Crée un nouveau client Docker
-    pub async fn new() -> Result<Self> {
-        let docker = Docker::connect_with_socket_defaults()
+    /// Crée un client connecté au Docker Engine d'un cluster : socket unix, TCP (avec TLS si des
+    /// certificats sont fournis) ou SSH ; le socket local par défaut si aucun hôte n'est donné
+    pub async fn connect(cluster: &ClusterConfig) -> Result<Self> {
+        let Some(host) = cluster.host.as_deref() else {
+            // Without a Docker socket nor DOCKER_HOST, fall back to the Podman socket
+            let podman_only = std::env::var_os("DOCKER_HOST").is_none()
+                && !Path::new(DOCKER_SOCKET).exists()
+                && Path::new(PODMAN_SOCKET).exists();
+            let docker = if podman_only {
+                Docker::connect_with_socket(PODMAN_SOCKET, CONNECT_TIMEOUT, API_DEFAULT_VERSION)
+            } else {
+                Docker::connect_with_socket_defaults()
+            }
             .map_err(|e| anyhow::anyhow!("Failed to connect to Docker daemon: {}", e))?;
+            return Ok(Self::from_docker(docker));
+        };
+
+        let scheme = host.split_once("://").map(|(scheme, _)| scheme).unwrap_or_default();
+        let docker = match (scheme, &cluster.tls) {
+            ("unix", _) => Docker::connect_with_socket(host, CONNECT_TIMEOUT, API_DEFAULT_VERSION),
+            ("tcp" | "https", Some(tls)) => Docker::connect_with_ssl(host, &tls.key, &tls.cert, &tls.ca, CONNECT_TIMEOUT, API_DEFAULT_VERSION),
+            ("tcp" | "http", None) => Docker::connect_with_http(host, CONNECT_TIMEOUT, API_DEFAULT_VERSION),
+            ("ssh", _) => {
+                let key = cluster.ssh_key.as_ref().map(|key| key.to_string_lossy().into_owned());
+                Docker::connect_with_ssh(host, CONNECT_TIMEOUT, API_DEFAULT_VERSION, key)
+            }
+            _ => return Err(anyhow::anyhow!(
+                "Cluster '{}': unsupported Docker host '{}' (expected unix://, tcp:// or ssh://)", cluster.name, host
+            )),
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to connect to Docker daemon of cluster '{}': {}", cluster.name, e))?;
+
+        Ok(Self::from_docker(docker))
+    }
+
+    fn from_docker(docker: Docker) -> Self {
+        Self { docker, engine: Arc::new(OnceCell::new()) }
+    }
 
-        Ok(Self { docker })
+    /// Détecte le moteur (Docker ou Podman) d'après la réponse de `/version`, une seule fois par client
+    async fn engine(&self) -> Result<Engine> {
+        let engine = self.engine.get_or_try_init(|| async {
+            let version = self.docker.version().await
+                .map_err(|e| anyhow::anyhow!("Failed to query Docker daemon version: {}", e))?;
+            let podman = version.components.unwrap_or_default().iter().any(|c| c.name.starts_with("Podman"))
+                || version.platform.is_some_and(|p| p.name.contains("Podman"));
+            Ok::<_, anyhow::Error>(if podman { Engine::Podman } else { Engine::Docker })
+        }).await?;
+        Ok(*engine)
+    }
+
+    /// Indique si le moteur est Podman
+    pub async fn is_podman(&self) -> Result<bool> {
+        Ok(self.engine().await? == Engine::Podman)
     }
 
     /// Retourne une référence au client Docker interne
@@ -20,4 +90,3 @@ impl DockerClient {
         &self.docker
     }
 }
-
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use std::sync::Arc;
+use crate::config::Config;
+use super::registry_auth::RegistryAuth;
+use super::{DockerClient, ImageService, StackService};
+
+/// Services Docker d'un cluster, partagés par tous les traitements qui le ciblent
+#[derive(Clone)]
+pub struct ClusterServices {
+    pub name: String,
+    pub image_service: Arc<ImageService>,
+    pub stack_service: Arc<StackService>,
+}
+
+/// Connexions aux Docker Engines de tous les clusters configurés
+pub struct Clusters {
+    clusters: Vec<ClusterServices>,
+}
+
+impl Clusters {
+    /// Se connecte à chaque cluster de la configuration
+    pub async fn connect(config: &Config) -> Result<Self> {
+        let auth = Arc::new(RegistryAuth::load(config)?);
+        let mut clusters = Vec::new();
+        for cluster in &config.clusters {
+            let client = DockerClient::connect(cluster).await?;
+            clusters.push(ClusterServices {
+                name: cluster.name.clone(),
+                image_service: Arc::new(ImageService::new(client.clone(), config.image_pull_policy, Arc::clone(&auth))),
+                stack_service: Arc::new(StackService::new(client, config.secrets_dir.clone(), Arc::clone(&auth), config.prepull)),
+            });
+        }
+        Ok(Self { clusters })
+    }
+
+    /// Services d'un cluster, par nom
+    pub fn get(&self, name: &str) -> Result<&ClusterServices> {
+        self.clusters.iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown cluster '{}' (not declared in DOCKEROPS_CLUSTERS)", name))
+    }
+}
@@ -0,0 +1,515 @@
+use anyhow::Result;
+use base64::Engine;
+use bollard::models::{
+    ContainerCreateBody, ContainerStateStatusEnum, EndpointSettings, HealthStatusEnum, HostConfig, HostConfigLogConfig,
+    Mount, MountTypeEnum, NetworkConnectRequest, NetworkingConfig, PortBinding, RestartPolicy, RestartPolicyNameEnum,
+    ServiceSpec, TaskSpecRestartPolicyConditionEnum,
+};
+use bollard::query_parameters::{
+    CreateContainerOptions, ListContainersOptions, ListNetworksOptions, RemoveContainerOptions,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::digest_processor::DigestProcessor;
+use crate::docker::client::DockerClient;
+use crate::docker::images::ImageService;
+use crate::docker::stack_spec::{StackSpec, NAMESPACE_LABEL};
+
+/// Labels posés par Docker Compose, repris pour que `docker compose ls/ps` reconnaisse les projets
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+const SERVICE_LABEL: &str = "com.docker.compose.service";
+const NUMBER_LABEL: &str = "com.docker.compose.container-number";
+const ONEOFF_LABEL: &str = "com.docker.compose.oneoff";
+const CONFIG_HASH_LABEL: &str = "com.docker.compose.config-hash";
+
+/// Réseaux connectés à un conteneur après sa création (nom, alias)
+type ExtraNetworks = Vec<(String, Vec<String>)>;
+
+/// Délai maximal de démarrage des conteneurs après un déploiement
+const DEPLOY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Service pour déployer les stacks comme projets Docker Compose sur un Docker Engine sans Swarm
+pub struct ComposeService {
+    client: DockerClient,
+    secrets_dir: PathBuf,
+}
+
+impl ComposeService {
+    /// Crée un nouveau service Compose ; les secrets externes sont lus dans `secrets_dir`
+    pub fn new(client: DockerClient, secrets_dir: PathBuf) -> Self {
+        Self { client, secrets_dir }
+    }
+
+    /// Déploie un projet Compose : crée les réseaux manquants, recrée les conteneurs des services dont
+    /// la configuration a changé, supprime ceux des services retirés du compose (comme `--remove-orphans`)
+    /// et attend leur démarrage. Les secrets et configs sont montés depuis des fichiers.
+    pub async fn deploy_project(&self, project: &str, compose_path: &Path) -> Result<()> {
+        println!("    [{}] Deploying Compose project", project);
+
+        let spec = Self::load_spec(project, compose_path)?;
+        if !spec.unsupported.is_empty() {
+            let unsupported: Vec<&str> = spec.unsupported.iter().map(String::as_str).collect();
+            println!("    [{}] Ignoring unsupported options: {}", project, unsupported.join(", "));
+        }
+
+        self.create_networks(project, &spec).await?;
+        self.remove_orphans(project, &spec).await?;
+        let files = self.secret_files(project, &spec)?;
+        for service in &spec.services {
+            self.sync_service(project, service, &files).await?;
+        }
+
+        self.wait_until_healthy(project, DEPLOY_TIMEOUT).await
+            .map_err(|e| anyhow::anyhow!("Failed to deploy project: {}", e))?;
+        println!("    [{}] Successfully deployed project", project);
+        Ok(())
+    }
+
+    /// Convertit le compose rendu d'un projet ; les chemins relatifs se résolvent depuis son dossier
+    fn load_spec(project: &str, compose_path: &Path) -> Result<StackSpec> {
+        let content = fs::read_to_string(compose_path)?;
+        let working_dir = compose_path.parent().unwrap_or(Path::new("."));
+        StackSpec::from_compose(project, &content, working_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to convert compose file of project '{}': {}", project, e))
+    }
+
+    /// Supprime les conteneurs du projet dont le service n'est plus dans le compose rendu
+    async fn remove_orphans(&self, project: &str, spec: &StackSpec) -> Result<()> {
+        let prefix = format!("{}_", project);
+        let services: Vec<&str> = spec.services.iter()
+            .filter_map(|service| service.name.as_deref())
+            .map(|name| name.strip_prefix(&prefix).unwrap_or(name))
+            .collect();
+
+        let docker = self.client.docker();
+        let containers = docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(Self::project_filter(project, None)),
+            ..Default::default()
+        })).await?;
+        for container in containers {
+            let labels = container.labels.unwrap_or_default();
+            // Containers started with `docker compose run` are left alone, as Compose does
+            if labels.get(ONEOFF_LABEL).is_some_and(|oneoff| oneoff == "True") {
+                continue;
+            }
+            let Some(service) = labels.get(SERVICE_LABEL).filter(|s| !services.contains(&s.as_str())) else {
+                continue;
+            };
+            if let Some(id) = container.id {
+                println!("    [{}] Removing container of service {} (no longer in the compose file)", project, service);
+                docker.remove_container(&id, Some(RemoveContainerOptions { force: true, ..Default::default() })).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Crée les réseaux du projet qui n'existent pas encore et vérifie la présence des réseaux externes
+    async fn create_networks(&self, project: &str, spec: &StackSpec) -> Result<()> {
+        let docker = self.client.docker();
+        for name in &spec.external_networks {
+            if docker.inspect_network(name, None).await.is_err() {
+                return Err(anyhow::anyhow!("Network '{}' is declared as external, but could not be found", name));
+            }
+        }
+
+        let options = ListNetworksOptions { filters: Some(Self::project_filter(project, None)) };
+        let existing: Vec<String> = docker.list_networks(Some(options)).await?
+            .into_iter()
+            .filter_map(|network| network.name)
+            .collect();
+
+        for network in &spec.networks {
+            if existing.contains(&network.name) {
+                continue;
+            }
+            println!("    [{}] Creating network {}", project, network.name);
+            let mut network = network.clone();
+            network.driver.get_or_insert_with(|| "bridge".to_string());
+            network.labels = Some(Self::project_labels(project, network.labels.take()));
+            let name = network.name.clone();
+            docker.create_network(network).await
+                .map_err(|e| anyhow::anyhow!("Failed to create network {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Fichier monté pour chaque secret et config (nom -> chemin sur l'hôte) : le fichier déclaré avec `file`,
+    /// le contenu inline écrit dans `secrets_dir/<nom>`, ou pour les externes `secrets_dir/<nom>`, qui doit exister
+    fn secret_files(&self, project: &str, spec: &StackSpec) -> Result<HashMap<String, PathBuf>> {
+        let mut files = spec.files.clone();
+
+        for name in spec.external_secrets.iter().chain(&spec.external_configs) {
+            let path = self.secrets_dir.join(name);
+            if !path.is_file() {
+                return Err(anyhow::anyhow!(
+                    "'{}' is declared as external, but {} does not exist (Swarm secrets are not available in compose mode)",
+                    name, path.display()
+                ));
+            }
+            files.insert(name.clone(), path);
+        }
+
+        let inline = spec.secrets.iter().map(|s| (&s.name, &s.data))
+            .chain(spec.configs.iter().map(|c| (&c.name, &c.data)));
+        for (name, data) in inline {
+            let (Some(name), Some(data)) = (name, data) else {
+                continue;
+            };
+            if files.contains_key(name) {
+                continue;
+            }
+            let content = base64::engine::general_purpose::STANDARD.decode(data)?;
+            fs::create_dir_all(&self.secrets_dir)?;
+            let path = self.secrets_dir.join(name);
+            fs::write(&path, content)
+                .map_err(|e| anyhow::anyhow!("[{}] Cannot write {}: {}", project, path.display(), e))?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o400))?;
+            files.insert(name.clone(), path);
+        }
+
+        Ok(files)
+    }
+
+    /// Recrée les conteneurs d'un service si sa configuration ou son nombre de réplicas a changé
+    async fn sync_service(&self, project: &str, service: &ServiceSpec, files: &HashMap<String, PathBuf>) -> Result<()> {
+        let docker = self.client.docker();
+        let full_name = service.name.clone().unwrap_or_default();
+        let service_name = full_name.strip_prefix(&format!("{}_", project)).unwrap_or(&full_name).to_string();
+
+        let (mut config, extra_networks) = Self::container_config(project, &service_name, service, files)?;
+        // Going through serde_json::Value sorts the keys of labels and other maps, for a stable hash
+        let config_value = serde_json::to_value((&config, &extra_networks))?;
+        let config_hash = DigestProcessor::sha256_hex(config_value.to_string().as_bytes());
+        if let Some(labels) = config.labels.as_mut() {
+            labels.insert(CONFIG_HASH_LABEL.to_string(), config_hash.clone());
+        }
+        let replicas = Self::replicas(service);
+
+        let existing = docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(Self::project_filter(project, Some(&service_name))),
+            ..Default::default()
+        })).await?;
+        let up_to_date = existing.len() == replicas
+            && existing.iter().all(|c| c.labels.as_ref().and_then(|l| l.get(CONFIG_HASH_LABEL)) == Some(&config_hash));
+        if up_to_date {
+            return Ok(());
+        }
+
+        if existing.is_empty() {
+            println!("    [{}] Creating service {}", project, service_name);
+        } else {
+            println!("    [{}] Recreating service {}", project, service_name);
+        }
+        for container in existing {
+            if let Some(id) = container.id {
+                docker.remove_container(&id, Some(RemoveContainerOptions { force: true, ..Default::default() })).await?;
+            }
+        }
+
+        // Podman does not always resolve short image names: use the name of the local image
+        if let Some(image) = config.image.take() {
+            config.image = Some(ImageService::local_name(&self.client, &image).await?);
+        }
+
+        for number in 1..=replicas {
+            let name = format!("{}-{}-{}", project, service_name, number);
+            let mut config = config.clone();
+            if let Some(labels) = config.labels.as_mut() {
+                labels.insert(NUMBER_LABEL.to_string(), number.to_string());
+            }
+            let options = CreateContainerOptions { name: Some(name.clone()), ..Default::default() };
+            docker.create_container(Some(options), config).await
+                .map_err(|e| anyhow::anyhow!("Failed to create container {}: {}", name, e))?;
+            for (network, aliases) in &extra_networks {
+                let request = NetworkConnectRequest {
+                    container: name.clone(),
+                    endpoint_config: Some(EndpointSettings { aliases: Some(aliases.clone()), ..Default::default() }),
+                };
+                docker.connect_network(network, request).await
+                    .map_err(|e| anyhow::anyhow!("Failed to connect container {} to {}: {}", name, network, e))?;
+            }
+            docker.start_container(&name, None).await
+                .map_err(|e| anyhow::anyhow!("Failed to start container {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Nombre de conteneurs d'un service : les réplicas du mode replicated, un seul sinon
+    fn replicas(service: &ServiceSpec) -> usize {
+        let mode = service.mode.as_ref();
+        let replicas = mode.and_then(|m| m.replicated.as_ref()).and_then(|r| r.replicas)
+            .or_else(|| mode.and_then(|m| m.replicated_job.as_ref()).and_then(|j| j.total_completions))
+            .unwrap_or(1);
+        replicas.max(0) as usize
+    }
+
+    /// Configuration des conteneurs d'un service et réseaux à connecter après leur création (nom -> alias)
+    fn container_config(
+        project: &str,
+        service_name: &str,
+        service: &ServiceSpec,
+        files: &HashMap<String, PathBuf>,
+    ) -> Result<(ContainerCreateBody, ExtraNetworks)> {
+        let task = service.task_template.clone().unwrap_or_default();
+        let container = task.container_spec.clone().unwrap_or_default();
+
+        let mut mounts: Vec<Mount> = container.mounts.clone().unwrap_or_default();
+        for mount in &mut mounts {
+            if let Some(options) = mount.volume_options.as_mut() {
+                options.labels = Some(Self::project_labels(project, options.labels.take()));
+            }
+        }
+        // Secrets and configs become read-only bind mounts of their files
+        let references = container.secrets.iter().flatten()
+            .map(|s| (s.secret_name.clone(), s.file.as_ref().and_then(|f| f.name.clone()), true))
+            .chain(container.configs.iter().flatten()
+                .map(|c| (c.config_name.clone(), c.file.as_ref().and_then(|f| f.name.clone()), false)));
+        for (name, target, is_secret) in references {
+            let (Some(name), Some(target)) = (name, target) else {
+                continue;
+            };
+            let source = files.get(&name).ok_or_else(|| anyhow::anyhow!("no file for '{}'", name))?;
+            let target = if is_secret && !target.starts_with('/') { format!("/run/secrets/{}", target) } else { target };
+            mounts.push(Mount {
+                target: Some(target),
+                source: Some(source.to_string_lossy().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                read_only: Some(true),
+                ..Default::default()
+            });
+        }
+
+        let mut exposed_ports = Vec::new();
+        let mut port_bindings = HashMap::new();
+        for port in service.endpoint_spec.iter().flat_map(|e| e.ports.iter().flatten()) {
+            let Some(target) = port.target_port else {
+                continue;
+            };
+            let key = format!("{}/{}", target, port.protocol.map(|p| p.to_string()).unwrap_or_else(|| "tcp".to_string()));
+            exposed_ports.push(key.clone());
+            let binding = PortBinding { host_ip: None, host_port: port.published_port.map(|p| p.to_string()) };
+            port_bindings.entry(key).or_insert_with(|| Some(Vec::new())).get_or_insert_with(Vec::new).push(binding);
+        }
+
+        let restart_policy = task.restart_policy.as_ref().map(|policy| match policy.condition {
+            Some(TaskSpecRestartPolicyConditionEnum::ON_FAILURE) => RestartPolicy {
+                name: Some(RestartPolicyNameEnum::ON_FAILURE),
+                maximum_retry_count: policy.max_attempts,
+            },
+            Some(TaskSpecRestartPolicyConditionEnum::NONE) => RestartPolicy {
+                name: Some(RestartPolicyNameEnum::NO),
+                ..Default::default()
+            },
+            _ => RestartPolicy { name: Some(RestartPolicyNameEnum::UNLESS_STOPPED), ..Default::default() },
+        });
+
+        let limits = task.resources.as_ref().and_then(|r| r.limits.clone()).unwrap_or_default();
+        let reservations = task.resources.as_ref().and_then(|r| r.reservations.clone()).unwrap_or_default();
+        let dns = container.dns_config.clone().unwrap_or_default();
+
+        let host_config = HostConfig {
+            mounts: Some(mounts),
+            port_bindings: Some(port_bindings),
+            restart_policy,
+            nano_cpus: limits.nano_cpus,
+            memory: limits.memory_bytes,
+            pids_limit: limits.pids,
+            memory_reservation: reservations.memory_bytes,
+            log_config: task.log_driver.as_ref().map(|driver| HostConfigLogConfig {
+                typ: driver.name.clone(),
+                config: driver.options.clone(),
+            }),
+            // Swarm hosts are written `ip host`, the Engine expects `host:ip`
+            extra_hosts: container.hosts.as_ref().map(|hosts| hosts.iter()
+                .filter_map(|entry| entry.split_once(' ').map(|(ip, host)| format!("{}:{}", host, ip)))
+                .collect()),
+            dns: dns.nameservers,
+            dns_search: dns.search,
+            dns_options: dns.options,
+            cap_add: container.capability_add.clone(),
+            cap_drop: container.capability_drop.clone(),
+            sysctls: container.sysctls.clone(),
+            ulimits: container.ulimits.clone(),
+            init: container.init,
+            readonly_rootfs: container.read_only,
+            group_add: container.groups.clone(),
+            oom_score_adj: container.oom_score_adj,
+            ..Default::default()
+        };
+
+        let mut labels = container.labels.clone().unwrap_or_default();
+        labels.remove(NAMESPACE_LABEL);
+        labels.insert(PROJECT_LABEL.to_string(), project.to_string());
+        labels.insert(SERVICE_LABEL.to_string(), service_name.to_string());
+        labels.insert(ONEOFF_LABEL.to_string(), "False".to_string());
+
+        // The first network is attached at creation, the others are connected before starting
+        let mut networks: ExtraNetworks = task.networks.iter().flatten()
+            .filter_map(|n| Some((n.target.clone()?, n.aliases.clone().unwrap_or_default())))
+            .collect();
+        let networking_config = (!networks.is_empty()).then(|| {
+            let (network, aliases) = networks.remove(0);
+            let endpoint = EndpointSettings { aliases: Some(aliases), ..Default::default() };
+            NetworkingConfig { endpoints_config: Some(HashMap::from([(network, endpoint)])) }
+        });
+
+        let config = ContainerCreateBody {
+            image: container.image.clone(),
+            entrypoint: container.command.clone(),
+            cmd: container.args.clone(),
+            env: container.env.clone(),
+            labels: Some(labels),
+            healthcheck: container.health_check.clone(),
+            hostname: container.hostname.clone(),
+            user: container.user.clone(),
+            working_dir: container.dir.clone(),
+            tty: container.tty,
+            open_stdin: container.open_stdin,
+            stop_signal: container.stop_signal.clone(),
+            stop_timeout: container.stop_grace_period.map(|ns| ns / 1_000_000_000),
+            exposed_ports: Some(exposed_ports),
+            host_config: Some(host_config),
+            networking_config,
+            ..Default::default()
+        };
+        Ok((config, networks))
+    }
+
+    /// Redémarre les conteneurs d'un service du projet
+    pub async fn restart_service(&self, project: &str, service_name: &str) -> Result<()> {
+        println!("    [{}] Restarting service {} (mounted files changed)", project, service_name);
+        let docker = self.client.docker();
+        let containers = docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(Self::project_filter(project, Some(service_name))),
+            ..Default::default()
+        })).await?;
+        for container in containers {
+            if let Some(id) = container.id {
+                docker.restart_container(&id, None).await
+                    .map_err(|e| anyhow::anyhow!("Failed to restart service {}: {}", service_name, e))?;
+            }
+        }
+        self.wait_until_healthy(project, DEPLOY_TIMEOUT).await
+    }
+
+    /// Recrée les conteneurs d'un service depuis le compose rendu `compose_path`, comme au déploiement :
+    /// sa configuration vient du compose, l'image référencée doit déjà être présente localement
+    pub async fn recreate_service(&self, project: &str, service_name: &str, compose_path: &Path) -> Result<()> {
+        let spec = Self::load_spec(project, compose_path)?;
+        let full_name = format!("{}_{}", project, service_name);
+        let service = spec.services.iter()
+            .find(|service| service.name.as_deref() == Some(full_name.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Service '{}' not found in compose file of project '{}'", service_name, project))?;
+
+        let files = self.secret_files(project, &spec)?;
+        self.sync_service(project, service, &files).await?;
+        self.wait_until_healthy(project, DEPLOY_TIMEOUT).await
+    }
+
+    /// Supprime les conteneurs puis les réseaux du projet ; les volumes sont conservés
+    pub async fn remove_project(&self, project: &str) -> Result<()> {
+        let docker = self.client.docker();
+        let containers = docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(Self::project_filter(project, None)),
+            ..Default::default()
+        })).await?;
+        for container in containers {
+            if let Some(id) = container.id {
+                docker.remove_container(&id, Some(RemoveContainerOptions { force: true, ..Default::default() })).await?;
+            }
+        }
+
+        let networks = docker.list_networks(Some(ListNetworksOptions { filters: Some(Self::project_filter(project, None)) })).await?;
+        for network in networks {
+            if let Some(id) = network.id {
+                docker.remove_network(&id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attend que tous les conteneurs du projet tournent (et soient healthy s'ils ont un healthcheck)
+    pub async fn wait_until_healthy(&self, project: &str, timeout: Duration) -> Result<()> {
+        println!("    [{}] Waiting for project to become healthy (timeout {}s)", project, timeout.as_secs());
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let pending = self.pending_containers(project).await?;
+            if pending.is_empty() {
+                println!("    [{}] Project is healthy", project);
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Project '{}' not healthy after {}s (waiting for: {})",
+                    project,
+                    timeout.as_secs(),
+                    pending.join(", ")
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    /// Liste les conteneurs du projet qui ne tournent pas encore ou dont le healthcheck n'est pas passé.
+    /// Un conteneur terminé avec le code 0 et sans redémarrage automatique (tâche ponctuelle) est considéré prêt.
+    async fn pending_containers(&self, project: &str) -> Result<Vec<String>> {
+        let docker = self.client.docker();
+        let containers = docker.list_containers(Some(ListContainersOptions {
+            all: true,
+            filters: Some(Self::project_filter(project, None)),
+            ..Default::default()
+        })).await?;
+        if containers.is_empty() {
+            return Ok(vec![format!("no containers found for project '{}'", project)]);
+        }
+
+        let mut pending = Vec::new();
+        for container in containers {
+            let Some(id) = container.id else {
+                continue;
+            };
+            let inspect = docker.inspect_container(&id, None).await?;
+            let name = inspect.name.unwrap_or(id).trim_start_matches('/').to_string();
+            let state = inspect.state.unwrap_or_default();
+            let restart = inspect.host_config.and_then(|h| h.restart_policy).and_then(|p| p.name);
+            let ready = match state.status {
+                Some(ContainerStateStatusEnum::RUNNING) => !matches!(
+                    state.health.and_then(|h| h.status),
+                    Some(HealthStatusEnum::STARTING | HealthStatusEnum::UNHEALTHY)
+                ),
+                Some(ContainerStateStatusEnum::EXITED) => state.exit_code == Some(0)
+                    && matches!(restart, None | Some(RestartPolicyNameEnum::NO | RestartPolicyNameEnum::EMPTY | RestartPolicyNameEnum::ON_FAILURE)),
+                _ => false,
+            };
+            if !ready {
+                pending.push(name);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Labels d'une ressource du projet (sans le label de stack Swarm)
+    fn project_labels(project: &str, labels: Option<HashMap<String, String>>) -> HashMap<String, String> {
+        let mut labels = labels.unwrap_or_default();
+        labels.remove(NAMESPACE_LABEL);
+        labels.insert(PROJECT_LABEL.to_string(), project.to_string());
+        labels
+    }
+
+    /// Filtre sur le label de projet (et de service)
+    fn project_filter(project: &str, service_name: Option<&str>) -> HashMap<String, Vec<String>> {
+        let mut labels = vec![format!("{}={}", PROJECT_LABEL, project)];
+        if let Some(service_name) = service_name {
+            labels.push(format!("{}={}", SERVICE_LABEL, service_name));
+        }
+        HashMap::from([("label".to_string(), labels)])
+    }
+}
@@ -2,34 +2,35 @@ use anyhow::Result;
 use bollard::query_parameters::{CreateImageOptions, ListImagesOptions, RemoveImageOptions};
 use bollard::models::ImageSummary;
 use futures::StreamExt;
+use std::sync::Arc;
 use crate::docker::client::DockerClient;
+use crate::docker::registry_auth::RegistryAuth;
 use crate::config::ImagePullPolicy;
 
 /// Service pour gérer les images Docker
 pub struct ImageService {
     client: DockerClient,
     pull_policy: ImagePullPolicy,
+    auth: Arc<RegistryAuth>,
 }
 
 impl ImageService {
     /// Crée un nouveau service d'images
-    pub fn new(client: DockerClient, pull_policy: ImagePullPolicy) -> Self {
+    pub fn new(client: DockerClient, pull_policy: ImagePullPolicy, auth: Arc<RegistryAuth>) -> Self {
         Self {
             client,
             pull_policy,
+            auth,
         }
     }
 
     /// Vérifie si une image existe localement
     pub async fn image_exists(&self, image_name: &str) -> Result<bool> {
-        let images: Vec<ImageSummary> = self.client.docker().list_images(None::<ListImagesOptions>).await?;
-        
-        Ok(images.iter().any(|img| {
-            img.repo_tags.iter().any(|tag| tag == image_name)
-        }))
+        Ok(Self::find_local_tag(&self.client, image_name).await?.is_some())
     }
 
     /// Récupère le SHA d'une image locale
+    #[allow(dead_code)]
     pub async fn get_local_image_sha(&self, image_name: &str) -> Result<Option<String>> {
         match self.client.docker().inspect_image(image_name).await {
             Ok(image) => {
@@ -40,6 +41,63 @@ impl ImageService {
         }
     }
 
+    /// Tag local d'une image, tel que le moteur l'a enregistré (`nginx:latest` pour Docker,
+    /// `docker.io/library/nginx:latest` ou `localhost/app:1` pour Podman)
+    pub(super) async fn find_local_tag(client: &DockerClient, image_name: &str) -> Result<Option<String>> {
+        let candidates = local_candidates(image_name, client.is_podman().await?);
+        let images: Vec<ImageSummary> = client.docker().list_images(None::<ListImagesOptions>).await?;
+
+        Ok(images.into_iter()
+            .flat_map(|img| img.repo_tags)
+            .find(|tag| candidates.contains(&qualified_reference(tag))))
+    }
+
+    /// Nom sous lequel désigner une image locale auprès du moteur : Podman ne résout pas toujours
+    /// les noms courts, son tag local est alors utilisé
+    pub(super) async fn local_name(client: &DockerClient, image_name: &str) -> Result<String> {
+        if !client.is_podman().await? {
+            return Ok(image_name.to_string());
+        }
+        Ok(Self::find_local_tag(client, image_name).await?.unwrap_or_else(|| image_name.to_string()))
+    }
+
+    /// Récupère le digest du registry d'une image locale (utilisable dans une référence image@digest).
+    /// Seul le digest du repository de l'image compte : une image aussi taguée sous un autre nom
+    /// a un digest différent par repository
+    pub async fn get_image_digest(&self, image_name: &str) -> Result<Option<String>> {
+        let repository = repository_name(image_name);
+        let local_name = Self::local_name(&self.client, image_name).await?;
+        match self.client.docker().inspect_image(&local_name).await {
+            Ok(image) => Ok(image.repo_digests.unwrap_or_default().into_iter()
+                .find_map(|d| {
+                    let (name, digest) = d.split_once('@')?;
+                    (repository_name(name) == repository).then(|| digest.to_string())
+                })),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Résout le digest actuel d'un tag auprès de son registry (manifest list pour les images multi-plateformes).
+    /// Si le moteur ne peut pas interroger le registry (Podman), l'image est pullée et son digest lu localement
+    pub async fn resolve_digest(&self, image_name: &str) -> Result<String> {
+        let credentials = self.auth.credentials_for(image_name).await;
+        match self.client.docker().inspect_registry_image(image_name, credentials).await {
+            Ok(inspect) => {
+                if let Some(digest) = inspect.descriptor.digest {
+                    return Ok(digest);
+                }
+            }
+            Err(e) if !self.client.is_podman().await? => {
+                return Err(anyhow::anyhow!("Failed to resolve digest of {}: {}", image_name, e));
+            }
+            Err(_) => {}
+        }
+
+        self.force_pull_image(image_name).await?;
+        self.get_image_digest(image_name).await?
+            .ok_or_else(|| anyhow::anyhow!("Failed to resolve digest of {}: no registry digest", image_name))
+    }
+
     /// Pull une image Docker selon la policy configurée
     pub async fn pull_image(&self, image_name: &str) -> Result<()> {
         match self.pull_policy {
@@ -60,13 +118,20 @@ impl ImageService {
     }
 
     /// Force le pull d'une image (toujours télécharger)
-    async fn force_pull_image(&self, image_name: &str) -> Result<()> {
+    pub async fn force_pull_image(&self, image_name: &str) -> Result<()> {
+        // Podman may refuse to resolve short names without a terminal: pull the fully qualified name
+        let from_image = if self.client.is_podman().await? {
+            qualified_reference(image_name)
+        } else {
+            image_name.to_string()
+        };
         let options = CreateImageOptions {
-            from_image: Some(image_name.to_string()),
+            from_image: Some(from_image),
             ..Default::default()
         };
 
-        let mut stream = self.client.docker().create_image(Some(options), None, None);
+        let credentials = self.auth.credentials_for(image_name).await;
+        let mut stream = self.client.docker().create_image(Some(options), None, credentials);
         
         while let Some(result) = stream.next().await {
             match result {
@@ -87,7 +152,8 @@ impl ImageService {
     pub async fn remove_image(&self, image_name: &str) -> Result<()> {
         println!("    Removing image: {}", image_name);
         
-        match self.client.docker().remove_image(image_name, None::<RemoveImageOptions>, None).await {
+        let local_name = Self::local_name(&self.client, image_name).await?;
+        match self.client.docker().remove_image(&local_name, None::<RemoveImageOptions>, None).await {
             Ok(_) => {
                 println!("    Successfully removed image: {}", image_name);
                 Ok(())
@@ -101,3 +167,59 @@ impl ImageService {
     }
 }
 
+/// Indique si le premier composant d'un nom d'image désigne un registry (`ghcr.io`, `registry:5000`, `localhost`)
+pub(super) fn has_registry(name: &str) -> bool {
+    name.split_once('/')
+        .is_some_and(|(first, _)| first.contains('.') || first.contains(':') || first == "localhost")
+}
+
+/// Forme complète d'une référence d'image, comme l'enregistre Podman :
+/// `nginx` -> `docker.io/library/nginx:latest`, `user/app:1` -> `docker.io/user/app:1`
+fn qualified_reference(image_name: &str) -> String {
+    let (name, digest) = match image_name.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image_name, None),
+    };
+
+    let mut qualified = if has_registry(name) {
+        match name.strip_prefix("docker.io/") {
+            Some(path) if !path.contains('/') => format!("docker.io/library/{}", path),
+            _ => name.to_string(),
+        }
+    } else if name.contains('/') {
+        format!("docker.io/{}", name)
+    } else {
+        format!("docker.io/library/{}", name)
+    };
+
+    let tagged = qualified.rsplit('/').next().is_some_and(|last| last.contains(':'));
+    if !tagged && digest.is_none() {
+        qualified.push_str(":latest");
+    }
+    if let Some(digest) = digest {
+        qualified.push('@');
+        qualified.push_str(digest);
+    }
+    qualified
+}
+
+/// Repository d'une référence d'image, sous sa forme complète et sans tag ni digest :
+/// `nginx:alpine` et `docker.io/library/nginx@sha256:...` -> `docker.io/library/nginx`
+fn repository_name(image_name: &str) -> String {
+    let name = image_name.split_once('@').map_or(image_name, |(name, _)| name);
+    let qualified = qualified_reference(name);
+    match qualified.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => repository.to_string(),
+        _ => qualified,
+    }
+}
+
+/// Formes complètes sous lesquelles une image peut être présente localement ; avec Podman,
+/// une image construite localement sans registry est enregistrée sous `localhost/`
+fn local_candidates(image_name: &str, podman: bool) -> Vec<String> {
+    let mut candidates = vec![qualified_reference(image_name)];
+    if podman && !has_registry(image_name) {
+        candidates.push(qualified_reference(&format!("localhost/{}", image_name)));
+    }
+    candidates
+}
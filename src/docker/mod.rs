@@ -1,8 +1,13 @@
 mod client;
+mod clusters;
+mod compose;
 mod images;
+mod prepull;
+mod registry_auth;
+mod stack_spec;
 mod stacks;
 
 pub use client::DockerClient;
+pub use clusters::{ClusterServices, Clusters};
 pub use images::ImageService;
 pub use stacks::StackService;
-
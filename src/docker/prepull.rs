@@ -0,0 +1,190 @@
+use anyhow::Result;
+use bollard::models::{
+    ServiceSpec, ServiceSpecMode, TaskSpec, TaskSpecContainerSpec, TaskSpecPlacement, TaskSpecRestartPolicy,
+    TaskSpecRestartPolicyConditionEnum, TaskState,
+};
+use bollard::query_parameters::{ListServicesOptions, ListTasksOptions};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::docker::client::DockerClient;
+use crate::docker::registry_auth::RegistryAuth;
+
+/// Délai maximal du pré-pull des images sur les nœuds
+const PREPULL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Délai après lequel un job sans tâche souhaitée est considéré sans nœud éligible
+const SCHEDULING_GRACE: Duration = Duration::from_secs(10);
+
+/// Label des jobs de pré-pull, dont la valeur est le nom du stack
+const PREPULL_LABEL: &str = "com.dockerops.prepull";
+
+/// Pré-pull des images d'un stack sur tous les nœuds du Swarm, avant la mise à jour des services.
+/// Chaque image est pullée par un job global (une tâche par nœud éligible) dont le conteneur ne fait
+/// rien : Swarm pulle l'image en préparant la tâche, avec les identifiants transmis à la création.
+pub(super) struct PrePuller {
+    client: DockerClient,
+    auth: Arc<RegistryAuth>,
+}
+
+/// Image à pré-puller, avec les contraintes de placement des services qui l'utilisent
+#[derive(PartialEq)]
+struct PrePullJob {
+    image: String,
+    placement: Option<TaskSpecPlacement>,
+}
+
+impl PrePuller {
+    pub fn new(client: DockerClient, auth: Arc<RegistryAuth>) -> Self {
+        Self { client, auth }
+    }
+
+    /// Pulle sur les nœuds éligibles les images des services nouvelles ou modifiées (`current` : service -> image
+    /// actuellement déployée) et attend que chaque nœud l'ait ; les jobs sont supprimés dans tous les cas
+    pub async fn prepull(&self, stack_name: &str, services: &[ServiceSpec], current: &HashMap<String, String>) -> Result<()> {
+        let mut jobs: Vec<PrePullJob> = Vec::new();
+        for service in services {
+            let Some(task) = service.task_template.as_ref() else {
+                continue;
+            };
+            let Some(image) = task.container_spec.as_ref().and_then(|c| c.image.clone()) else {
+                continue;
+            };
+            let name = service.name.clone().unwrap_or_default();
+            if current.get(&name) == Some(&image) {
+                continue;
+            }
+            // Only constraints and platforms select nodes: preferences and max_replicas do not apply to a global job
+            let placement = task.placement.as_ref().map(|p| TaskSpecPlacement {
+                constraints: p.constraints.clone(),
+                platforms: p.platforms.clone(),
+                ..Default::default()
+            });
+            let job = PrePullJob { image, placement };
+            if !jobs.contains(&job) {
+                jobs.push(job);
+            }
+        }
+        if jobs.is_empty() {
+            return Ok(());
+        }
+
+        // Jobs left behind by an interrupted run would otherwise keep their name
+        self.remove_jobs(stack_name).await?;
+
+        println!("    [{}] Pre-pulling {} image(s) on every node", stack_name, jobs.len());
+        let result = self.run_jobs(stack_name, &jobs).await;
+        if let Err(e) = self.remove_jobs(stack_name).await {
+            println!("    [{}] Warning: Could not remove pre-pull jobs: {}", stack_name, e);
+        }
+        result?;
+        println!("    [{}] Images pulled on every node", stack_name);
+        Ok(())
+    }
+
+    /// Crée un job global par image puis attend que chaque nœud ait dépassé l'étape de pull
+    async fn run_jobs(&self, stack_name: &str, jobs: &[PrePullJob]) -> Result<()> {
+        let docker = self.client.docker();
+        let mut names = Vec::new();
+        for (index, job) in jobs.iter().enumerate() {
+            let name = format!("{}_prepull-{}", stack_name, index + 1);
+            let spec = ServiceSpec {
+                name: Some(name.clone()),
+                labels: Some(HashMap::from([(PREPULL_LABEL.to_string(), stack_name.to_string())])),
+                mode: Some(ServiceSpecMode { global_job: Some(HashMap::new()), ..Default::default() }),
+                task_template: Some(TaskSpec {
+                    container_spec: Some(TaskSpecContainerSpec {
+                        image: Some(job.image.clone()),
+                        command: Some(vec!["true".to_string()]),
+                        ..Default::default()
+                    }),
+                    placement: job.placement.clone(),
+                    restart_policy: Some(TaskSpecRestartPolicy {
+                        condition: Some(TaskSpecRestartPolicyConditionEnum::NONE),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let credentials = self.auth.credentials_for(&job.image).await;
+            docker.create_service(spec, credentials).await
+                .map_err(|e| anyhow::anyhow!("Failed to create pre-pull job for {}: {}", job.image, e))?;
+            names.push((name, job.image.as_str()));
+        }
+
+        let started = Instant::now();
+        let deadline = started + PREPULL_TIMEOUT;
+        loop {
+            let mut pending = Vec::new();
+            for (name, image) in &names {
+                if !self.job_done(name, image, started.elapsed() >= SCHEDULING_GRACE).await? {
+                    pending.push(*image);
+                }
+            }
+            if pending.is_empty() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Images not pulled on every node after {}s: {}",
+                    PREPULL_TIMEOUT.as_secs(),
+                    pending.join(", ")
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    }
+
+    /// Indique si chaque nœud éligible a pullé l'image d'un job ; une tâche rejetée (pull impossible) est une erreur
+    async fn job_done(&self, name: &str, image: &str, scheduled: bool) -> Result<bool> {
+        let docker = self.client.docker();
+        let filters = HashMap::from([("name".to_string(), vec![name.to_string()])]);
+        let desired = docker.list_services(Some(ListServicesOptions { filters: Some(filters), status: Some(true) })).await?
+            .into_iter()
+            .find(|s| s.spec.as_ref().and_then(|spec| spec.name.as_deref()) == Some(name))
+            .and_then(|s| s.service_status)
+            .and_then(|status| status.desired_tasks)
+            .unwrap_or(0);
+
+        let filters = HashMap::from([("service".to_string(), vec![name.to_string()])]);
+        let tasks = docker.list_tasks(Some(ListTasksOptions { filters: Some(filters) })).await?;
+        let mut pulled: HashSet<String> = HashSet::new();
+        for task in tasks {
+            let status = task.status.unwrap_or_default();
+            match status.state {
+                Some(TaskState::REJECTED) => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to pull {} on node {}: {}",
+                        image,
+                        task.node_id.unwrap_or_default(),
+                        status.err.unwrap_or_default()
+                    ));
+                }
+                // Past the preparing state the image is on the node, whatever the container did
+                Some(TaskState::READY | TaskState::STARTING | TaskState::RUNNING | TaskState::COMPLETE | TaskState::FAILED) => {
+                    pulled.extend(task.node_id);
+                }
+                _ => {}
+            }
+        }
+
+        if desired == 0 {
+            // No eligible node once the orchestrator had time to schedule the job
+            return Ok(scheduled);
+        }
+        Ok(pulled.len() as u64 >= desired)
+    }
+
+    /// Supprime les jobs de pré-pull d'un stack
+    async fn remove_jobs(&self, stack_name: &str) -> Result<()> {
+        let docker = self.client.docker();
+        let filters = HashMap::from([("label".to_string(), vec![format!("{}={}", PREPULL_LABEL, stack_name)])]);
+        for service in docker.list_services(Some(ListServicesOptions { filters: Some(filters), status: None })).await? {
+            if let Some(id) = service.id {
+                docker.delete_service(&id).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use base64::Engine;
+use bollard::auth::DockerCredentials;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use crate::config::Config;
+use crate::docker::images::has_registry;
+
+/// Hôte de Docker Hub, et adresse sous laquelle Docker enregistre ses identifiants
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+
+/// `config.json` de Docker (seules les sections d'authentification sont lues)
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfigFile {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+    #[serde(default)]
+    cred_helpers: HashMap<String, String>,
+    #[serde(default)]
+    creds_store: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AuthEntry {
+    #[serde(default)]
+    auth: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    identitytoken: Option<String>,
+}
+
+/// Réponse de `docker-credential-<helper> get`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+    username: String,
+    secret: String,
+}
+
+/// Identifiants des registries privés, utilisés pour les pulls et transmis à Swarm
+/// pour que les nœuds workers puissent aussi puller les images
+pub struct RegistryAuth {
+    /// Hôte -> identifiants (`DOCKEROPS_REGISTRIES`, puis `auths` de config.json)
+    credentials: HashMap<String, DockerCredentials>,
+    /// Hôte -> credential helper (`credHelpers` de config.json)
+    helpers: HashMap<String, String>,
+    /// Credential helper des autres hôtes (`credsStore` de config.json)
+    default_helper: Option<String>,
+    /// Identifiants déjà obtenus des credential helpers, par hôte
+    helper_cache: Mutex<HashMap<String, Option<DockerCredentials>>>,
+}
+
+impl RegistryAuth {
+    /// Charge les identifiants de la configuration et du `config.json` de Docker ; un `config.json`
+    /// illisible est ignoré avec un avertissement
+    pub fn load(config: &Config) -> Result<Self> {
+        let docker_config = match fs::read_to_string(&config.docker_config) {
+            Ok(content) => serde_json::from_str::<DockerConfigFile>(&content).unwrap_or_else(|e| {
+                println!("Warning: Ignoring invalid {}: {}", config.docker_config.display(), e);
+                DockerConfigFile::default()
+            }),
+            Err(_) => DockerConfigFile::default(),
+        };
+
+        let mut credentials = HashMap::new();
+        for (server, entry) in docker_config.auths {
+            let host = normalize_host(&server);
+            let (username, password) = match (&entry.auth, entry.username, entry.password) {
+                (Some(auth), _, _) => {
+                    let decoded = base64::engine::general_purpose::STANDARD.decode(auth.trim())
+                        .ok()
+                        .and_then(|bytes| String::from_utf8(bytes).ok())
+                        .unwrap_or_default();
+                    match decoded.split_once(':') {
+                        Some((username, password)) => (Some(username.to_string()), Some(password.to_string())),
+                        None => (None, None),
+                    }
+                }
+                (None, username, password) => (username, password),
+            };
+            if username.is_none() && entry.identitytoken.is_none() {
+                continue;
+            }
+            credentials.insert(host.clone(), DockerCredentials {
+                username,
+                password,
+                identitytoken: entry.identitytoken,
+                serveraddress: Some(server_address(&host)),
+                ..Default::default()
+            });
+        }
+
+        // Credentials declared for DockerOps take precedence over the Docker CLI ones
+        for registry in &config.registries {
+            let password = match (&registry.password, &registry.password_file) {
+                (Some(password), _) => password.clone(),
+                (None, Some(path)) => fs::read_to_string(path)
+                    .map_err(|e| anyhow::anyhow!("Registry '{}': cannot read {}: {}", registry.host, path.display(), e))?
+                    .trim_end()
+                    .to_string(),
+                (None, None) => String::new(),
+            };
+            let host = normalize_host(&registry.host);
+            credentials.insert(host.clone(), DockerCredentials {
+                username: Some(registry.username.clone()),
+                password: Some(password),
+                serveraddress: Some(server_address(&host)),
+                ..Default::default()
+            });
+        }
+
+        Ok(Self {
+            credentials,
+            helpers: docker_config.cred_helpers.into_iter().map(|(server, helper)| (normalize_host(&server), helper)).collect(),
+            default_helper: docker_config.creds_store.filter(|helper| !helper.is_empty()),
+            helper_cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Identifiants du registry d'une image, ou None pour un accès anonyme
+    pub async fn credentials_for(&self, image_name: &str) -> Option<DockerCredentials> {
+        let host = registry_host(image_name);
+        if let Some(credentials) = self.credentials.get(&host) {
+            return Some(credentials.clone());
+        }
+
+        let helper = self.helpers.get(&host).or(self.default_helper.as_ref())?;
+        let mut cache = self.helper_cache.lock().await;
+        if let Some(credentials) = cache.get(&host) {
+            return credentials.clone();
+        }
+
+        let credentials = match Self::run_helper(helper, &host).await {
+            Ok(credentials) => credentials,
+            Err(e) => {
+                println!("    Warning: Credential helper '{}' failed for {}: {:#}", helper, host, e);
+                None
+            }
+        };
+        cache.insert(host, credentials.clone());
+        credentials
+    }
+
+    /// Interroge `docker-credential-<helper> get` ; None si le helper ne connaît pas le registry
+    async fn run_helper(helper: &str, host: &str) -> Result<Option<DockerCredentials>> {
+        let server = server_address(host);
+        let mut child = Command::new(format!("docker-credential-{}", helper))
+            .arg("get")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(server.as_bytes()).await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            // Helpers report unknown registries with "credentials not found" on stdout
+            let message = String::from_utf8_lossy(&output.stdout);
+            if message.contains("credentials not found") {
+                return Ok(None);
+            }
+            return Err(anyhow::anyhow!("{}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+
+        let helper_credentials: HelperCredentials = serde_json::from_slice(&output.stdout)?;
+        // A "<token>" username means the secret is an identity token
+        let credentials = if helper_credentials.username == "<token>" {
+            DockerCredentials { identitytoken: Some(helper_credentials.secret), ..Default::default() }
+        } else {
+            DockerCredentials {
+                username: Some(helper_credentials.username),
+                password: Some(helper_credentials.secret),
+                ..Default::default()
+            }
+        };
+        Ok(Some(DockerCredentials { serveraddress: Some(server), ..credentials }))
+    }
+}
+
+/// Hôte du registry d'une image (`docker.io` pour les images sans registry)
+fn registry_host(image_name: &str) -> String {
+    match image_name.split_once('/') {
+        Some((first, _)) if has_registry(image_name) => normalize_host(first),
+        _ => DOCKER_HUB.to_string(),
+    }
+}
+
+/// Hôte d'une adresse de registry : sans schéma ni chemin, les alias de Docker Hub ramenés à `docker.io`
+fn normalize_host(server: &str) -> String {
+    let host = server.trim().trim_start_matches("https://").trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or_default().to_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => DOCKER_HUB.to_string(),
+        _ => host,
+    }
+}
+
+/// Adresse du registry transmise au daemon et aux credential helpers
+fn server_address(host: &str) -> String {
+    if host == DOCKER_HUB {
+        DOCKER_HUB_SERVER.to_string()
+    } else {
+        host.to_string()
+    }
+}
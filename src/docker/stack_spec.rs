@@ -0,0 +1,1006 @@
+use anyhow::Result;
+use base64::Engine;
+use bollard::models::{
+    ConfigSpec, EndpointPortConfig, EndpointSpec, HealthConfig, Limit, Mount, MountBindOptions, MountTmpfsOptions,
+    MountTypeEnum, MountVolumeOptions, MountVolumeOptionsDriverConfig, NetworkAttachmentConfig, NetworkCreateRequest,
+    ResourceObject, ResourcesUlimits, SecretSpec, ServiceSpec, ServiceSpecMode, ServiceSpecModeReplicated,
+    ServiceSpecModeReplicatedJob, ServiceSpecRollbackConfig, ServiceSpecUpdateConfig, TaskSpec,
+    TaskSpecContainerSpec, TaskSpecContainerSpecConfigs, TaskSpecContainerSpecDnsConfig, TaskSpecContainerSpecFile,
+    TaskSpecContainerSpecFile1, TaskSpecContainerSpecSecrets, TaskSpecLogDriver, TaskSpecPlacement,
+    TaskSpecPlacementPreferences, TaskSpecPlacementSpread, TaskSpecResources, TaskSpecRestartPolicy,
+};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::compose_processor::ComposeProcessor;
+use crate::env_processor::EnvProcessor;
+
+/// Label identifiant les ressources d'un stack, comme avec `docker stack deploy`
+pub const NAMESPACE_LABEL: &str = "com.docker.stack.namespace";
+const IMAGE_LABEL: &str = "com.docker.stack.image";
+
+/// Options de service appliquées au déploiement Swarm ; les autres sont ignorées (`build`, `container_name`...)
+const SUPPORTED_SERVICE_KEYS: [&str; 40] = [
+    "image", "command", "entrypoint", "environment", "env_file", "labels", "deploy", "healthcheck", "ports",
+    "expose", "volumes", "tmpfs", "secrets", "configs", "networks", "logging", "extra_hosts", "dns", "dns_search",
+    "dns_opt", "hostname", "user", "working_dir", "tty", "stdin_open", "read_only", "init", "stop_signal",
+    "stop_grace_period", "cap_add", "cap_drop", "sysctls", "ulimits", "group_add", "restart", "scale",
+    "oom_score_adj", "extends", "pull_policy", "depends_on",
+];
+
+/// Ressources Swarm d'un stack, converties depuis un fichier compose selon les conventions de
+/// `docker stack deploy` : ressources nommées `<stack>_<nom>` et marquées du label `com.docker.stack.namespace`,
+/// réseau `<stack>_default` pour les services sans réseau. Sert aussi au mode compose, qui crée des conteneurs
+/// à partir des mêmes spécifications.
+pub struct StackSpec {
+    pub networks: Vec<NetworkCreateRequest>,
+    pub external_networks: Vec<String>,
+    pub secrets: Vec<SecretSpec>,
+    pub external_secrets: Vec<String>,
+    pub configs: Vec<ConfigSpec>,
+    pub external_configs: Vec<String>,
+    /// Fichiers source des secrets et configs déclarés avec `file` (nom -> chemin)
+    pub files: HashMap<String, PathBuf>,
+    /// Services ; les références aux secrets et configs sont résolues par nom au déploiement
+    pub services: Vec<ServiceSpec>,
+    /// Options compose sans équivalent Swarm, ignorées
+    pub unsupported: BTreeSet<String>,
+}
+
+impl StackSpec {
+    /// Convertit un fichier compose rendu ; les chemins relatifs sont résolus depuis `working_dir`
+    pub fn from_compose(namespace: &str, content: &str, working_dir: &Path) -> Result<Self> {
+        let mut document: Value = serde_yaml::from_str(content)?;
+        // The rendered compose keeps Compose escaping (`$$`), which the docker CLI used to undo
+        Self::unescape(&mut document);
+
+        let converter = Converter { namespace, working_dir, document: &document };
+        let mut spec = StackSpec {
+            networks: Vec::new(),
+            external_networks: Vec::new(),
+            secrets: Vec::new(),
+            external_secrets: Vec::new(),
+            configs: Vec::new(),
+            external_configs: Vec::new(),
+            files: HashMap::new(),
+            services: Vec::new(),
+            unsupported: BTreeSet::new(),
+        };
+
+        let services = document.get("services").and_then(|s| s.as_mapping()).cloned().unwrap_or_default();
+        let mut used_networks = BTreeSet::new();
+        for (name, service) in &services {
+            let name = name.as_str().ok_or_else(|| anyhow::anyhow!("Service names must be strings"))?;
+            for key in service.as_mapping().into_iter().flat_map(|m| m.keys()).filter_map(|k| k.as_str()) {
+                if !SUPPORTED_SERVICE_KEYS.contains(&key) && !key.starts_with("x-") {
+                    spec.unsupported.insert(key.to_string());
+                }
+            }
+            used_networks.extend(converter.service_network_keys(service));
+            spec.services.push(converter.service(name, service)
+                .map_err(|e| anyhow::anyhow!("service '{}': {:#}", name, e))?);
+        }
+
+        for key in used_networks {
+            let definition = converter.definition("networks", &key);
+            let (name, external) = converter.resource_name("networks", &key)?;
+            if external {
+                spec.external_networks.push(name);
+                continue;
+            }
+            if definition.get("ipam").is_some() {
+                spec.unsupported.insert(format!("networks.{}.ipam", key));
+            }
+            spec.networks.push(NetworkCreateRequest {
+                name,
+                // Without a driver, each backend applies its own default (overlay or bridge)
+                driver: str_of(definition.get("driver")),
+                internal: bool_of(definition.get("internal")),
+                attachable: bool_of(definition.get("attachable")),
+                options: Some(string_map(definition.get("driver_opts"))),
+                labels: Some(converter.labels(definition.get("labels"))),
+                ..Default::default()
+            });
+        }
+
+        for (section, references) in [("secrets", "secrets"), ("configs", "configs")] {
+            let keys: BTreeSet<String> = services.values()
+                .flat_map(|service| service.get(references).and_then(|r| r.as_sequence()).cloned().unwrap_or_default())
+                .filter_map(|reference| reference_source(&reference))
+                .collect();
+            for key in keys {
+                let (name, external) = converter.resource_name(section, &key)?;
+                if external {
+                    match section {
+                        "secrets" => spec.external_secrets.push(name),
+                        _ => spec.external_configs.push(name),
+                    }
+                    continue;
+                }
+                let data = converter.file_data(section, &key)?;
+                if let Some(file) = str_of(converter.definition(section, &key).get("file")) {
+                    spec.files.insert(name.clone(), converter.resolve(&file));
+                }
+                let labels = Some(converter.labels(converter.definition(section, &key).get("labels")));
+                match section {
+                    "secrets" => spec.secrets.push(SecretSpec { name: Some(name), labels, data: Some(data), ..Default::default() }),
+                    _ => spec.configs.push(ConfigSpec { name: Some(name), labels, data: Some(data), ..Default::default() }),
+                }
+            }
+        }
+
+        Ok(spec)
+    }
+
+    /// Remplace les `$$` des chaînes par `$`
+    fn unescape(value: &mut Value) {
+        match value {
+            Value::String(s) if s.contains("$$") => *s = s.replace("$$", "$"),
+            Value::Sequence(items) => items.iter_mut().for_each(Self::unescape),
+            Value::Mapping(mapping) => mapping.values_mut().for_each(Self::unescape),
+            Value::Tagged(tagged) => Self::unescape(&mut tagged.value),
+            _ => {}
+        }
+    }
+}
+
+struct Converter<'a> {
+    namespace: &'a str,
+    working_dir: &'a Path,
+    document: &'a Value,
+}
+
+impl Converter<'_> {
+    fn scoped(&self, name: &str) -> String {
+        format!("{}_{}", self.namespace, name)
+    }
+
+    /// Labels d'une ressource, avec le label du stack
+    fn labels(&self, labels: Option<&Value>) -> HashMap<String, String> {
+        let mut labels = string_map(labels);
+        labels.insert(NAMESPACE_LABEL.to_string(), self.namespace.to_string());
+        labels
+    }
+
+    /// Définition de premier niveau (`networks.<clé>`...), vide si absente
+    fn definition(&self, section: &str, key: &str) -> Value {
+        self.document.get(section).and_then(|s| s.get(key)).filter(|d| !d.is_null()).cloned()
+            .unwrap_or(Value::Mapping(Mapping::new()))
+    }
+
+    /// Nom Swarm d'une ressource de premier niveau et indication `external`
+    fn resource_name(&self, section: &str, key: &str) -> Result<(String, bool)> {
+        let declared = self.document.get(section).and_then(|s| s.as_mapping()).is_some_and(|s| s.contains_key(key));
+        if !(declared || section == "networks" && key == "default") {
+            return Err(anyhow::anyhow!("{} '{}' is used but not declared", section, key));
+        }
+
+        let definition = self.definition(section, key);
+        let external = match definition.get("external") {
+            Some(Value::Mapping(external)) => {
+                // Legacy `external: { name: ... }`
+                if let Some(name) = external.get("name").and_then(|n| n.as_str()) {
+                    return Ok((name.to_string(), true));
+                }
+                true
+            }
+            other => bool_of(other).unwrap_or(false),
+        };
+        let name = match str_of(definition.get("name")) {
+            Some(name) => name,
+            None if external => key.to_string(),
+            None => self.scoped(key),
+        };
+        Ok((name, external))
+    }
+
+    /// Contenu encodé en base64 du fichier d'un secret ou d'une config
+    fn file_data(&self, section: &str, key: &str) -> Result<String> {
+        let definition = self.definition(section, key);
+        let content = if let Some(file) = str_of(definition.get("file")) {
+            let path = self.resolve(&file);
+            fs::read(&path).map_err(|e| anyhow::anyhow!("{} '{}': cannot read {}: {}", section, key, path.display(), e))?
+        } else if let Some(content) = str_of(definition.get("content")) {
+            content.into_bytes()
+        } else {
+            return Err(anyhow::anyhow!("{} '{}' needs either 'file' or 'external'", section, key));
+        };
+        Ok(base64::engine::general_purpose::STANDARD.encode(content))
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+            None => PathBuf::from(path),
+        };
+        ComposeProcessor::normalize_path(&self.working_dir.join(path))
+    }
+
+    /// Clés des réseaux d'un service (`default` s'il n'en déclare aucun)
+    fn service_network_keys(&self, service: &Value) -> Vec<String> {
+        match service.get("networks") {
+            Some(Value::Sequence(networks)) => networks.iter().filter_map(|n| n.as_str().map(String::from)).collect(),
+            Some(Value::Mapping(networks)) => networks.keys().filter_map(|n| n.as_str().map(String::from)).collect(),
+            _ => vec!["default".to_string()],
+        }
+    }
+
+    fn service(&self, name: &str, service: &Value) -> Result<ServiceSpec> {
+        let deploy = service.get("deploy").cloned().unwrap_or(Value::Null);
+        let image = str_of(service.get("image")).ok_or_else(|| anyhow::anyhow!("'image' is required"))?;
+
+        let mut service_labels = self.labels(deploy.get("labels"));
+        service_labels.insert(IMAGE_LABEL.to_string(), image.clone());
+
+        let container_spec = TaskSpecContainerSpec {
+            image: Some(image),
+            labels: Some(self.labels(service.get("labels"))),
+            command: command_of(service.get("entrypoint")),
+            args: command_of(service.get("command")),
+            hostname: str_of(service.get("hostname")),
+            env: Some(self.environment(service)?),
+            dir: str_of(service.get("working_dir")),
+            user: str_of(service.get("user")),
+            groups: list_of(service.get("group_add")),
+            tty: bool_of(service.get("tty")),
+            open_stdin: bool_of(service.get("stdin_open")),
+            read_only: bool_of(service.get("read_only")),
+            mounts: Some(self.mounts(service)?),
+            stop_signal: str_of(service.get("stop_signal")),
+            stop_grace_period: duration_of(service.get("stop_grace_period"))?,
+            health_check: healthcheck(service.get("healthcheck"))?,
+            hosts: extra_hosts(service.get("extra_hosts")),
+            dns_config: Some(TaskSpecContainerSpecDnsConfig {
+                nameservers: list_of(service.get("dns")),
+                search: list_of(service.get("dns_search")),
+                options: list_of(service.get("dns_opt")),
+            }),
+            secrets: Some(self.secret_references(service)?),
+            configs: Some(self.config_references(service)?),
+            oom_score_adj: int_of(service.get("oom_score_adj"))?,
+            init: bool_of(service.get("init")),
+            sysctls: service.get("sysctls").map(|s| string_map(Some(s))),
+            capability_add: list_of(service.get("cap_add")),
+            capability_drop: list_of(service.get("cap_drop")),
+            ulimits: ulimits(service.get("ulimits"))?,
+            ..Default::default()
+        };
+
+        let networks = self.service_network_keys(service).into_iter()
+            .map(|key| {
+                let (target, _) = self.resource_name("networks", &key)?;
+                let mut aliases = vec![name.to_string()];
+                aliases.extend(service.get("networks").and_then(|n| n.get(&key)).and_then(|n| list_of(n.get("aliases"))).unwrap_or_default());
+                Ok(NetworkAttachmentConfig { target: Some(target), aliases: Some(aliases), ..Default::default() })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let task_template = TaskSpec {
+            container_spec: Some(container_spec),
+            resources: resources(deploy.get("resources"))?,
+            restart_policy: restart_policy(deploy.get("restart_policy"), service.get("restart"))?,
+            placement: placement(deploy.get("placement"))?,
+            networks: Some(networks),
+            log_driver: service.get("logging").map(|logging| TaskSpecLogDriver {
+                name: str_of(logging.get("driver")),
+                options: Some(string_map(logging.get("options"))),
+            }),
+            ..Default::default()
+        };
+
+        Ok(ServiceSpec {
+            name: Some(self.scoped(name)),
+            labels: Some(service_labels),
+            task_template: Some(task_template),
+            mode: Some(mode(&deploy, service.get("scale"))?),
+            update_config: update_config(deploy.get("update_config"))?,
+            rollback_config: rollback_config(deploy.get("rollback_config"))?,
+            endpoint_spec: Some(EndpointSpec {
+                mode: str_of(deploy.get("endpoint_mode")).map(|m| m.parse()).transpose().map_err(anyhow::Error::msg)?,
+                ports: Some(ports(service.get("ports"))?),
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Variables des `env_file` puis de `environment`, au format `CLÉ=valeur` triées par clé
+    fn environment(&self, service: &Value) -> Result<Vec<String>> {
+        let mut variables = HashMap::new();
+        let env_files: Vec<Value> = match service.get("env_file") {
+            Some(Value::Sequence(files)) => files.clone(),
+            Some(file) if !file.is_null() => vec![file.clone()],
+            _ => Vec::new(),
+        };
+        for env_file in env_files {
+            let (file, required) = match &env_file {
+                Value::Mapping(_) => (str_of(env_file.get("path")), bool_of(env_file.get("required")).unwrap_or(true)),
+                other => (str_of(Some(other)), true),
+            };
+            let Some(file) = file else {
+                continue;
+            };
+            let path = self.resolve(&file);
+            match fs::read_to_string(&path) {
+                Ok(content) => EnvProcessor::parse_env_file(&content, &mut variables)
+                    .map_err(|e| anyhow::anyhow!("invalid env file {}: {}", path.display(), e))?,
+                Err(_) if !required => {}
+                Err(e) => return Err(anyhow::anyhow!("cannot read env file {}: {}", path.display(), e)),
+            }
+        }
+
+        match service.get("environment") {
+            Some(Value::Mapping(environment)) => {
+                for (key, value) in environment {
+                    if let (Some(key), Some(value)) = (key.as_str(), str_of(Some(value))) {
+                        variables.insert(key.to_string(), value);
+                    }
+                }
+            }
+            Some(Value::Sequence(environment)) => {
+                for entry in environment.iter().filter_map(|e| e.as_str()) {
+                    // A bare `KEY` would come from the docker CLI's environment: nothing to pass
+                    if let Some((key, value)) = entry.split_once('=') {
+                        variables.insert(key.to_string(), value.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let mut environment: Vec<String> = variables.into_iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        environment.sort();
+        Ok(environment)
+    }
+
+    fn mounts(&self, service: &Value) -> Result<Vec<Mount>> {
+        let mut mounts = Vec::new();
+        for volume in service.get("volumes").and_then(|v| v.as_sequence()).into_iter().flatten() {
+            let mount = match volume {
+                Value::String(spec) => self.short_mount(spec)?,
+                Value::Mapping(_) => self.long_mount(volume)?,
+                _ => return Err(anyhow::anyhow!("invalid volume entry")),
+            };
+            mounts.push(mount);
+        }
+
+        for target in list_of(service.get("tmpfs")).unwrap_or_default() {
+            mounts.push(Mount { target: Some(target), typ: Some(MountTypeEnum::TMPFS), ..Default::default() });
+        }
+
+        Ok(mounts)
+    }
+
+    /// `cible`, `source:cible` ou `source:cible:options`
+    fn short_mount(&self, spec: &str) -> Result<Mount> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let (source, target, options) = match parts.as_slice() {
+            [target] => (None, *target, ""),
+            [source, target] => (Some(*source), *target, ""),
+            [source, target, options] => (Some(*source), *target, *options),
+            _ => return Err(anyhow::anyhow!("invalid volume specification '{}'", spec)),
+        };
+        let options: Vec<&str> = options.split(',').filter(|o| !o.is_empty()).collect();
+        let read_only = options.contains(&"ro");
+
+        match source {
+            Some(source) if is_path(source) => Ok(Mount {
+                target: Some(target.to_string()),
+                source: Some(self.resolve(source).to_string_lossy().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                read_only: Some(read_only),
+                bind_options: options.iter().find_map(|o| o.parse().ok())
+                    .map(|propagation| MountBindOptions { propagation: Some(propagation), ..Default::default() }),
+                ..Default::default()
+            }),
+            source => Ok(Mount {
+                target: Some(target.to_string()),
+                read_only: Some(read_only),
+                ..self.volume_mount(source, options.contains(&"nocopy"), None)?
+            }),
+        }
+    }
+
+    fn long_mount(&self, volume: &Value) -> Result<Mount> {
+        let target = str_of(volume.get("target")).ok_or_else(|| anyhow::anyhow!("volume 'target' is required"))?;
+        let source = str_of(volume.get("source"));
+        let read_only = bool_of(volume.get("read_only"));
+        let kind = str_of(volume.get("type")).unwrap_or_else(|| "volume".to_string());
+
+        let mount = match kind.as_str() {
+            "bind" => Mount {
+                source: source.map(|s| self.resolve(&s).to_string_lossy().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                bind_options: str_of(volume.get("bind").and_then(|b| b.get("propagation")))
+                    .map(|p| p.parse().map_err(anyhow::Error::msg)).transpose()?
+                    .map(|propagation| MountBindOptions { propagation: Some(propagation), ..Default::default() }),
+                ..Default::default()
+            },
+            "volume" => {
+                let options = volume.get("volume");
+                self.volume_mount(
+                    source.as_deref(),
+                    bool_of(options.and_then(|o| o.get("nocopy"))).unwrap_or(false),
+                    str_of(options.and_then(|o| o.get("subpath"))),
+                )?
+            }
+            "tmpfs" => Mount {
+                typ: Some(MountTypeEnum::TMPFS),
+                tmpfs_options: Some(MountTmpfsOptions {
+                    size_bytes: bytes_of(volume.get("tmpfs").and_then(|t| t.get("size")))?,
+                    mode: int_of(volume.get("tmpfs").and_then(|t| t.get("mode")))?,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            other => return Err(anyhow::anyhow!("unsupported volume type '{}'", other)),
+        };
+
+        Ok(Mount { target: Some(target), read_only, ..mount })
+    }
+
+    /// Volume nommé (déclaré au premier niveau) ou anonyme
+    fn volume_mount(&self, source: Option<&str>, no_copy: bool, subpath: Option<String>) -> Result<Mount> {
+        let Some(key) = source else {
+            return Ok(Mount { typ: Some(MountTypeEnum::VOLUME), ..Default::default() });
+        };
+        let (name, external) = self.resource_name("volumes", key)?;
+        let definition = self.definition("volumes", key);
+        let driver_config = str_of(definition.get("driver")).map(|driver| MountVolumeOptionsDriverConfig {
+            name: Some(driver),
+            options: Some(string_map(definition.get("driver_opts"))),
+        });
+
+        Ok(Mount {
+            source: Some(name),
+            typ: Some(MountTypeEnum::VOLUME),
+            volume_options: Some(MountVolumeOptions {
+                no_copy: Some(no_copy),
+                labels: (!external).then(|| self.labels(definition.get("labels"))),
+                driver_config: if external { None } else { driver_config },
+                subpath,
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn secret_references(&self, service: &Value) -> Result<Vec<TaskSpecContainerSpecSecrets>> {
+        let mut references = Vec::new();
+        for reference in service.get("secrets").and_then(|s| s.as_sequence()).into_iter().flatten() {
+            let key = reference_source(reference).ok_or_else(|| anyhow::anyhow!("invalid secret reference"))?;
+            let (name, _) = self.resource_name("secrets", &key)?;
+            references.push(TaskSpecContainerSpecSecrets {
+                file: Some(TaskSpecContainerSpecFile {
+                    name: Some(str_of(reference.get("target")).unwrap_or_else(|| key.clone())),
+                    uid: Some(str_of(reference.get("uid")).unwrap_or_else(|| "0".to_string())),
+                    gid: Some(str_of(reference.get("gid")).unwrap_or_else(|| "0".to_string())),
+                    mode: Some(file_mode(reference.get("mode"))?),
+                }),
+                secret_name: Some(name),
+                secret_id: None,
+            });
+        }
+        Ok(references)
+    }
+
+    fn config_references(&self, service: &Value) -> Result<Vec<TaskSpecContainerSpecConfigs>> {
+        let mut references = Vec::new();
+        for reference in service.get("configs").and_then(|s| s.as_sequence()).into_iter().flatten() {
+            let key = reference_source(reference).ok_or_else(|| anyhow::anyhow!("invalid config reference"))?;
+            let (name, _) = self.resource_name("configs", &key)?;
+            references.push(TaskSpecContainerSpecConfigs {
+                file: Some(TaskSpecContainerSpecFile1 {
+                    name: Some(str_of(reference.get("target")).unwrap_or_else(|| format!("/{}", key))),
+                    uid: Some(str_of(reference.get("uid")).unwrap_or_else(|| "0".to_string())),
+                    gid: Some(str_of(reference.get("gid")).unwrap_or_else(|| "0".to_string())),
+                    mode: Some(file_mode(reference.get("mode"))?),
+                }),
+                config_name: Some(name),
+                ..Default::default()
+            });
+        }
+        Ok(references)
+    }
+}
+
+/// Nom de la ressource référencée par un service (`nom` ou `{ source: nom }`)
+fn reference_source(reference: &Value) -> Option<String> {
+    reference.as_str().map(String::from).or_else(|| str_of(reference.get("source")))
+}
+
+fn is_path(source: &str) -> bool {
+    source.starts_with('.') || source.starts_with('/') || source.starts_with('~')
+}
+
+/// Valeur scalaire sous forme de chaîne
+fn str_of(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Tagged(tagged) => str_of(Some(&tagged.value)),
+        _ => None,
+    }
+}
+
+fn bool_of(value: Option<&Value>) -> Option<bool> {
+    match value? {
+        Value::Bool(b) => Some(*b),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn int_of(value: Option<&Value>) -> Result<Option<i64>> {
+    match str_of(value) {
+        Some(s) => s.parse().map(Some).map_err(|_| anyhow::anyhow!("invalid number '{}'", s)),
+        None => Ok(None),
+    }
+}
+
+/// Liste de chaînes (une chaîne seule donne une liste d'un élément)
+fn list_of(value: Option<&Value>) -> Option<Vec<String>> {
+    match value? {
+        Value::Sequence(items) => Some(items.iter().filter_map(|i| str_of(Some(i))).collect()),
+        other => str_of(Some(other)).map(|s| vec![s]),
+    }
+}
+
+/// Mapping ou liste `clé=valeur`
+fn string_map(value: Option<&Value>) -> HashMap<String, String> {
+    match value {
+        Some(Value::Mapping(mapping)) => mapping.iter()
+            .filter_map(|(k, v)| Some((str_of(Some(k))?, str_of(Some(v)).unwrap_or_default())))
+            .collect(),
+        Some(Value::Sequence(items)) => items.iter()
+            .filter_map(|i| i.as_str())
+            .map(|i| match i.split_once('=') {
+                Some((k, v)) => (k.to_string(), v.to_string()),
+                None => (i.to_string(), String::new()),
+            })
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+/// `command` / `entrypoint` : liste telle quelle, chaîne découpée comme par un shell
+fn command_of(value: Option<&Value>) -> Option<Vec<String>> {
+    match value? {
+        Value::Sequence(items) => Some(items.iter().filter_map(|i| str_of(Some(i))).collect()),
+        other => str_of(Some(other)).map(|s| shell_words(&s)),
+    }
+}
+
+fn shell_words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => current.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+/// Durée compose (`1m30s`, `500ms`, ou nombre de secondes) en nanosecondes
+fn duration_of(value: Option<&Value>) -> Result<Option<i64>> {
+    let Some(s) = str_of(value) else {
+        return Ok(None);
+    };
+    if let Ok(seconds) = s.parse::<f64>() {
+        return Ok(Some((seconds * 1e9) as i64));
+    }
+
+    let mut total = 0f64;
+    let mut rest = s.as_str();
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(split);
+        let unit_len = tail.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let number: f64 = number.parse().map_err(|_| anyhow::anyhow!("invalid duration '{}'", s))?;
+        let nanos = match unit {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return Err(anyhow::anyhow!("invalid duration '{}'", s)),
+        };
+        total += number * nanos;
+        rest = tail;
+    }
+    Ok(Some(total as i64))
+}
+
+/// Taille (`512m`, `1.5G`, `1024`) en octets
+fn bytes_of(value: Option<&Value>) -> Result<Option<i64>> {
+    let Some(s) = str_of(value) else {
+        return Ok(None);
+    };
+    let lower = s.trim().to_lowercase();
+    let lower = lower.strip_suffix('b').filter(|l| l.ends_with(['k', 'm', 'g', 't'])).unwrap_or(&lower);
+    let split = lower.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+    let number: f64 = number.parse().map_err(|_| anyhow::anyhow!("invalid size '{}'", s))?;
+    let multiplier = match unit {
+        "" | "b" => 1.0,
+        "k" => 1024.0,
+        "m" => 1024.0 * 1024.0,
+        "g" => 1024.0 * 1024.0 * 1024.0,
+        "t" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return Err(anyhow::anyhow!("invalid size '{}'", s)),
+    };
+    Ok(Some((number * multiplier) as i64))
+}
+
+fn nano_cpus_of(value: Option<&Value>) -> Result<Option<i64>> {
+    match str_of(value) {
+        Some(s) => s.parse::<f64>().map(|cpus| Some((cpus * 1e9) as i64))
+            .map_err(|_| anyhow::anyhow!("invalid cpus '{}'", s)),
+        None => Ok(None),
+    }
+}
+
+/// Mode d'un fichier de secret ou config (octal, 0444 par défaut)
+fn file_mode(value: Option<&Value>) -> Result<u32> {
+    match str_of(value) {
+        // YAML 1.2 reads `0440` as the decimal 440: digits are taken as octal, as Compose does
+        Some(s) => u32::from_str_radix(s.trim_start_matches("0o"), 8).map_err(|_| anyhow::anyhow!("invalid file mode '{}'", s)),
+        None => Ok(0o444),
+    }
+}
+
+fn healthcheck(value: Option<&Value>) -> Result<Option<HealthConfig>> {
+    let Some(healthcheck) = value.filter(|v| !v.is_null()) else {
+        return Ok(None);
+    };
+    if bool_of(healthcheck.get("disable")).unwrap_or(false) {
+        return Ok(Some(HealthConfig { test: Some(vec!["NONE".to_string()]), ..Default::default() }));
+    }
+    let test = match healthcheck.get("test") {
+        Some(Value::Sequence(_)) => list_of(healthcheck.get("test")),
+        other => str_of(other).map(|command| vec!["CMD-SHELL".to_string(), command]),
+    };
+    Ok(Some(HealthConfig {
+        test,
+        interval: duration_of(healthcheck.get("interval"))?,
+        timeout: duration_of(healthcheck.get("timeout"))?,
+        retries: int_of(healthcheck.get("retries"))?,
+        start_period: duration_of(healthcheck.get("start_period"))?,
+        start_interval: duration_of(healthcheck.get("start_interval"))?,
+    }))
+}
+
+/// `host:ip` (ou `host=ip`) devient `ip host`, format attendu par Swarm
+fn extra_hosts(value: Option<&Value>) -> Option<Vec<String>> {
+    let hosts: Vec<(String, String)> = match value? {
+        Value::Mapping(_) => string_map(value).into_iter().collect(),
+        _ => list_of(value)?.into_iter()
+            .filter_map(|entry| {
+                let (host, ip) = entry.split_once('=').or_else(|| entry.split_once(':'))?;
+                Some((host.to_string(), ip.to_string()))
+            })
+            .collect(),
+    };
+    Some(hosts.into_iter().map(|(host, ip)| format!("{} {}", ip, host)).collect())
+}
+
+fn ulimits(value: Option<&Value>) -> Result<Option<Vec<ResourcesUlimits>>> {
+    let Some(Value::Mapping(ulimits)) = value else {
+        return Ok(None);
+    };
+    let mut limits = Vec::new();
+    for (name, limit) in ulimits {
+        let (soft, hard) = match limit {
+            Value::Mapping(_) => (int_of(limit.get("soft"))?, int_of(limit.get("hard"))?),
+            other => {
+                let value = int_of(Some(other))?;
+                (value, value)
+            }
+        };
+        limits.push(ResourcesUlimits { name: str_of(Some(name)), soft, hard });
+    }
+    Ok(Some(limits))
+}
+
+fn resources(value: Option<&Value>) -> Result<Option<TaskSpecResources>> {
+    let Some(resources) = value.filter(|v| !v.is_null()) else {
+        return Ok(None);
+    };
+    let limits = resources.get("limits");
+    let reservations = resources.get("reservations");
+    Ok(Some(TaskSpecResources {
+        limits: Some(Limit {
+            nano_cpus: nano_cpus_of(limits.and_then(|l| l.get("cpus")))?,
+            memory_bytes: bytes_of(limits.and_then(|l| l.get("memory")))?,
+            pids: int_of(limits.and_then(|l| l.get("pids")))?,
+        }),
+        reservations: Some(ResourceObject {
+            nano_cpus: nano_cpus_of(reservations.and_then(|r| r.get("cpus")))?,
+            memory_bytes: bytes_of(reservations.and_then(|r| r.get("memory")))?,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }))
+}
+
+/// `deploy.restart_policy`, ou à défaut l'option `restart` du service
+fn restart_policy(value: Option<&Value>, restart: Option<&Value>) -> Result<Option<TaskSpecRestartPolicy>> {
+    if let Some(policy) = value.filter(|v| !v.is_null()) {
+        return Ok(Some(TaskSpecRestartPolicy {
+            condition: str_of(policy.get("condition")).map(|c| c.parse()).transpose().map_err(anyhow::Error::msg)?,
+            delay: duration_of(policy.get("delay"))?,
+            max_attempts: int_of(policy.get("max_attempts"))?,
+            window: duration_of(policy.get("window"))?,
+        }));
+    }
+
+    let Some(restart) = str_of(restart) else {
+        return Ok(None);
+    };
+    let (condition, max_attempts) = match restart.split_once(':') {
+        Some(("on-failure", attempts)) => ("on-failure", int_of(Some(&Value::from(attempts)))?),
+        _ => match restart.as_str() {
+            "no" => ("none", None),
+            "always" | "unless-stopped" => ("any", None),
+            "on-failure" => ("on-failure", None),
+            other => return Err(anyhow::anyhow!("invalid restart policy '{}'", other)),
+        },
+    };
+    Ok(Some(TaskSpecRestartPolicy {
+        condition: Some(condition.parse().map_err(anyhow::Error::msg)?),
+        max_attempts,
+        ..Default::default()
+    }))
+}
+
+fn placement(value: Option<&Value>) -> Result<Option<TaskSpecPlacement>> {
+    let Some(placement) = value.filter(|v| !v.is_null()) else {
+        return Ok(None);
+    };
+    let preferences = placement.get("preferences").and_then(|p| p.as_sequence()).map(|preferences| {
+        preferences.iter()
+            .map(|preference| TaskSpecPlacementPreferences {
+                spread: str_of(preference.get("spread")).map(|descriptor| TaskSpecPlacementSpread {
+                    spread_descriptor: Some(descriptor),
+                }),
+            })
+            .collect()
+    });
+    Ok(Some(TaskSpecPlacement {
+        constraints: list_of(placement.get("constraints")),
+        preferences,
+        max_replicas: int_of(placement.get("max_replicas_per_node"))?,
+        ..Default::default()
+    }))
+}
+
+fn mode(deploy: &Value, scale: Option<&Value>) -> Result<ServiceSpecMode> {
+    let replicas = match int_of(deploy.get("replicas"))? {
+        Some(replicas) => Some(replicas),
+        None => int_of(scale)?,
+    };
+    let mode = str_of(deploy.get("mode")).unwrap_or_else(|| "replicated".to_string());
+    Ok(match mode.as_str() {
+        "replicated" => ServiceSpecMode {
+            replicated: Some(ServiceSpecModeReplicated { replicas }),
+            ..Default::default()
+        },
+        "global" => ServiceSpecMode { global: Some(HashMap::new()), ..Default::default() },
+        "replicated-job" => ServiceSpecMode {
+            replicated_job: Some(ServiceSpecModeReplicatedJob {
+                max_concurrent: replicas,
+                total_completions: replicas,
+            }),
+            ..Default::default()
+        },
+        "global-job" => ServiceSpecMode { global_job: Some(HashMap::new()), ..Default::default() },
+        other => return Err(anyhow::anyhow!("invalid deploy mode '{}'", other)),
+    })
+}
+
+fn update_config(value: Option<&Value>) -> Result<Option<ServiceSpecUpdateConfig>> {
+    let Some(config) = value.filter(|v| !v.is_null()) else {
+        return Ok(None);
+    };
+    Ok(Some(ServiceSpecUpdateConfig {
+        parallelism: Some(int_of(config.get("parallelism"))?.unwrap_or(1)),
+        delay: duration_of(config.get("delay"))?,
+        failure_action: str_of(config.get("failure_action")).map(|a| a.parse()).transpose().map_err(anyhow::Error::msg)?,
+        monitor: duration_of(config.get("monitor"))?,
+        max_failure_ratio: str_of(config.get("max_failure_ratio")).and_then(|r| r.parse().ok()),
+        order: str_of(config.get("order")).map(|o| o.parse()).transpose().map_err(anyhow::Error::msg)?,
+    }))
+}
+
+fn rollback_config(value: Option<&Value>) -> Result<Option<ServiceSpecRollbackConfig>> {
+    let Some(config) = value.filter(|v| !v.is_null()) else {
+        return Ok(None);
+    };
+    Ok(Some(ServiceSpecRollbackConfig {
+        parallelism: Some(int_of(config.get("parallelism"))?.unwrap_or(1)),
+        delay: duration_of(config.get("delay"))?,
+        failure_action: str_of(config.get("failure_action")).map(|a| a.parse()).transpose().map_err(anyhow::Error::msg)?,
+        monitor: duration_of(config.get("monitor"))?,
+        max_failure_ratio: str_of(config.get("max_failure_ratio")).and_then(|r| r.parse().ok()),
+        order: str_of(config.get("order")).map(|o| o.parse()).transpose().map_err(anyhow::Error::msg)?,
+    }))
+}
+
+/// Ports publiés : `[ip:]publié:cible[/protocole]` (plages comprises) ou syntaxe longue
+fn ports(value: Option<&Value>) -> Result<Vec<EndpointPortConfig>> {
+    let mut ports = Vec::new();
+    for port in value.and_then(|v| v.as_sequence()).into_iter().flatten() {
+        if port.is_mapping() {
+            let target = int_of(port.get("target"))?.ok_or_else(|| anyhow::anyhow!("port 'target' is required"))?;
+            ports.push(EndpointPortConfig {
+                target_port: Some(target),
+                published_port: int_of(port.get("published"))?,
+                protocol: Some(str_of(port.get("protocol")).unwrap_or_else(|| "tcp".to_string()).parse().map_err(anyhow::Error::msg)?),
+                publish_mode: Some(str_of(port.get("mode")).unwrap_or_else(|| "ingress".to_string()).parse().map_err(anyhow::Error::msg)?),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let spec = str_of(Some(port)).ok_or_else(|| anyhow::anyhow!("invalid port entry"))?;
+        let (mapping, protocol) = spec.split_once('/').unwrap_or((&spec, "tcp"));
+        let parts: Vec<&str> = mapping.rsplitn(3, ':').collect();
+        let (target, published) = match parts.as_slice() {
+            [target] => (*target, None),
+            // The host IP of `ip:published:target` has no meaning in Swarm
+            [target, published, ..] => (*target, Some(*published).filter(|p| !p.is_empty())),
+            _ => return Err(anyhow::anyhow!("invalid port '{}'", spec)),
+        };
+
+        let targets = port_range(target, &spec)?;
+        let published = published.map(|p| port_range(p, &spec)).transpose()?;
+        if published.as_ref().is_some_and(|p| p.len() != targets.len() && p.len() != 1) {
+            return Err(anyhow::anyhow!("invalid port range '{}'", spec));
+        }
+        for (index, target) in targets.iter().enumerate() {
+            ports.push(EndpointPortConfig {
+                target_port: Some(*target),
+                published_port: published.as_ref().map(|p| if p.len() == 1 { p[0] } else { p[index] }),
+                protocol: Some(protocol.parse().map_err(anyhow::Error::msg)?),
+                publish_mode: Some("ingress".parse().map_err(anyhow::Error::msg)?),
+                ..Default::default()
+            });
+        }
+    }
+    Ok(ports)
+}
+
+fn port_range(range: &str, spec: &str) -> Result<Vec<i64>> {
+    let invalid = || anyhow::anyhow!("invalid port '{}'", spec);
+    match range.split_once('-') {
+        Some((start, end)) => {
+            let start: i64 = start.parse().map_err(|_| invalid())?;
+            let end: i64 = end.parse().map_err(|_| invalid())?;
+            Ok((start..=end).collect())
+        }
+        None => Ok(vec![range.parse().map_err(|_| invalid())?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{EndpointPortConfigProtocolEnum, EndpointPortConfigPublishModeEnum};
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    fn published(ports: &[EndpointPortConfig]) -> Vec<(Option<i64>, Option<i64>)> {
+        ports.iter().map(|p| (p.published_port, p.target_port)).collect()
+    }
+
+    fn ports_of(spec: &str) -> Result<Vec<EndpointPortConfig>> {
+        ports(Some(&Value::Sequence(vec![Value::String(spec.to_string())])))
+    }
+
+    #[test]
+    fn parses_short_port_syntax() {
+        let ports = ports(Some(&yaml(r#"["80", "8080:80", "127.0.0.1:8443:443", "9000-9001:9000-9001", "53:53/udp"]"#))).unwrap();
+        assert_eq!(published(&ports), vec![
+            (None, Some(80)),
+            (Some(8080), Some(80)),
+            (Some(8443), Some(443)),
+            (Some(9000), Some(9000)),
+            (Some(9001), Some(9001)),
+            (Some(53), Some(53)),
+        ]);
+        assert_eq!(ports[0].protocol, Some(EndpointPortConfigProtocolEnum::TCP));
+        assert_eq!(ports[5].protocol, Some(EndpointPortConfigProtocolEnum::UDP));
+        assert_eq!(ports[0].publish_mode, Some(EndpointPortConfigPublishModeEnum::INGRESS));
+
+        assert!(ports_of("abc:80").is_err());
+        assert!(ports_of("9000-9002:80-81").is_err());
+    }
+
+    #[test]
+    fn parses_long_port_syntax() {
+        let ports = ports(Some(&yaml("- target: 80\n  published: 8080\n  protocol: udp\n  mode: host\n- target: 443\n"))).unwrap();
+        assert_eq!(published(&ports), vec![(Some(8080), Some(80)), (None, Some(443))]);
+        assert_eq!(ports[0].protocol, Some(EndpointPortConfigProtocolEnum::UDP));
+        assert_eq!(ports[0].publish_mode, Some(EndpointPortConfigPublishModeEnum::HOST));
+        assert_eq!(ports[1].protocol, Some(EndpointPortConfigProtocolEnum::TCP));
+
+        assert!(super::ports(Some(&yaml("- published: 8080\n"))).is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(duration_of(Some(&yaml("1m30s"))).unwrap(), Some(90_000_000_000));
+        assert_eq!(duration_of(Some(&yaml("500ms"))).unwrap(), Some(500_000_000));
+        assert_eq!(duration_of(Some(&yaml("1h2m"))).unwrap(), Some(3_720_000_000_000));
+        assert_eq!(duration_of(Some(&yaml("10"))).unwrap(), Some(10_000_000_000));
+        assert_eq!(duration_of(None).unwrap(), None);
+        assert!(duration_of(Some(&yaml("10 minutes"))).is_err());
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!(bytes_of(Some(&yaml("512M"))).unwrap(), Some(512 * 1024 * 1024));
+        assert_eq!(bytes_of(Some(&yaml("512mb"))).unwrap(), Some(512 * 1024 * 1024));
+        assert_eq!(bytes_of(Some(&yaml("1.5g"))).unwrap(), Some(1536 * 1024 * 1024));
+        assert_eq!(bytes_of(Some(&yaml("64k"))).unwrap(), Some(64 * 1024));
+        assert_eq!(bytes_of(Some(&yaml("1024"))).unwrap(), Some(1024));
+        assert!(bytes_of(Some(&yaml("12x"))).is_err());
+    }
+
+    #[test]
+    fn parses_file_modes_as_octal() {
+        assert_eq!(file_mode(Some(&yaml("0444"))).unwrap(), 0o444);
+        assert_eq!(file_mode(Some(&yaml("\"0o440\""))).unwrap(), 0o440);
+        assert_eq!(file_mode(Some(&yaml("\"0600\""))).unwrap(), 0o600);
+        assert_eq!(file_mode(None).unwrap(), 0o444);
+        assert!(file_mode(Some(&yaml("0999"))).is_err());
+    }
+
+    #[test]
+    fn splits_command_strings_like_a_shell() {
+        assert_eq!(shell_words(r#"sh -c "echo 'hello world'""#), vec!["sh", "-c", "echo 'hello world'"]);
+        assert_eq!(shell_words(r#"echo 'a  b' c\ d "" end"#), vec!["echo", "a  b", "c d", "", "end"]);
+        assert_eq!(shell_words(r#"printf "\"quoted\"""#), vec!["printf", "\"quoted\""]);
+        assert_eq!(command_of(Some(&yaml(r#"["echo", "a b"]"#))), Some(vec!["echo".to_string(), "a b".to_string()]));
+    }
+
+    #[test]
+    fn unescapes_dollars() {
+        let mut value = yaml("command: echo $$HOME\nenvironment: [\"PRICE=5$$\"]\n");
+        StackSpec::unescape(&mut value);
+        assert_eq!(value["command"], Value::String("echo $HOME".to_string()));
+        assert_eq!(value["environment"][0], Value::String("PRICE=5$".to_string()));
+    }
+}
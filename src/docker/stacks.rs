@@ -1,54 +1,484 @@
 use anyhow::Result;
-use std::path::Path;
-use std::process::Command;
+use bollard::auth::DockerCredentials;
+use bollard::models::{Service, ServiceSpec, ServiceUpdateStatusStateEnum};
+use bollard::query_parameters::{
+    ListConfigsOptions, ListNetworksOptions, ListSecretsOptions, ListServicesOptions, ListTasksOptions,
+    UpdateConfigOptions, UpdateSecretOptions, UpdateServiceOptions,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::config::DeployBackend;
 use crate::docker::client::DockerClient;
+use crate::docker::compose::ComposeService;
+use crate::docker::prepull::PrePuller;
+use crate::docker::registry_auth::RegistryAuth;
+use crate::docker::stack_spec::{StackSpec, NAMESPACE_LABEL};
 
-/// Service pour gérer les stacks Docker Swarm
+/// Délai maximal de convergence des services après un déploiement
+const DEPLOY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Délai maximal d'arrêt des tâches d'un stack supprimé
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Services mis à jour par un déploiement, avec leur version et le début de leur dernière mise à jour avant celui-ci
+type Updated = HashMap<String, (u64, Option<String>)>;
+
+/// Service pour gérer les stacks via l'API du Docker Engine, en mode Swarm ou Compose
 pub struct StackService {
     client: DockerClient,
+    compose: ComposeService,
+    /// Identifiants transmis à Swarm avec chaque service, pour que les workers puissent puller son image
+    auth: Arc<RegistryAuth>,
+    /// Pré-pull des images sur tous les nœuds avant la mise à jour des services (`DOCKEROPS_PREPULL`)
+    prepuller: Option<PrePuller>,
 }
 
 impl StackService {
-    /// Crée un nouveau service de stacks
-    pub fn new(client: DockerClient) -> Self {
-        Self { client }
-    }
-
-    /// Déploie un stack Docker Swarm. Les secrets sont gérés nativement par Swarm (external) et exposés via l'entrypoint généré.
-    pub async fn deploy_stack(&self, stack_name: &str, compose_path: &Path) -> Result<()> {
-        println!("    Deploying stack '{}' with docker stack deploy", stack_name);
-
-        let output = Command::new("docker")
-            .args(&["stack", "deploy", "--detach=false", "-c", compose_path.to_str().unwrap(), stack_name])
-            .output()?;
-        
-        if output.status.success() {
-            println!("    Successfully deployed stack '{}'", stack_name);
-            Ok(())
-        } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            println!("    Error deploying stack '{}': {}", stack_name, error);
-            Err(anyhow::anyhow!("Failed to deploy stack: {}", error))
-        }
-    }
-
-    /// Arrête un stack Docker Swarm
-    pub async fn stop_stack(&self, stack_name: &str) -> Result<()> {
-        println!("    Stopping stack '{}' with docker stack rm", stack_name);
-        
-        let output = Command::new("docker")
-            .args(&["stack", "rm", stack_name])
-            .output()?;
-        
-        if output.status.success() {
-            println!("    Successfully stopped stack '{}'", stack_name);
-        } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            println!("    Warning: Error stopping stack '{}': {}", stack_name, error);
+    /// Crée un nouveau service de stacks ; `secrets_dir` contient les secrets montés en mode compose
+    pub fn new(client: DockerClient, secrets_dir: PathBuf, auth: Arc<RegistryAuth>, prepull: bool) -> Self {
+        let compose = ComposeService::new(client.clone(), secrets_dir);
+        let prepuller = prepull.then(|| PrePuller::new(client.clone(), Arc::clone(&auth)));
+        Self { client, compose, auth, prepuller }
+    }
+
+    /// Identifiants du registry de l'image d'un service (comme `docker stack deploy --with-registry-auth`)
+    async fn service_credentials(&self, service: &ServiceSpec) -> Option<DockerCredentials> {
+        let image = service.task_template.as_ref()?.container_spec.as_ref()?.image.as_deref()?;
+        self.auth.credentials_for(image).await
+    }
+
+    /// Déploie un stack Docker Swarm : crée les réseaux, secrets et configs manquants, puis crée ou met à jour
+    /// chaque service en place, supprime les services retirés du fichier compose (comme `docker stack deploy --prune`)
+    /// et attend leur convergence. En mode compose, le stack est déployé comme projet Compose.
+    pub async fn deploy_stack(&self, backend: DeployBackend, stack_name: &str, compose_path: &Path) -> Result<()> {
+        if backend == DeployBackend::Compose {
+            return self.compose.deploy_project(stack_name, compose_path).await;
+        }
+        if self.client.is_podman().await? {
+            return Err(anyhow::anyhow!("Podman does not support Swarm: deploy this stack with the compose backend (backend: compose)"));
+        }
+        println!("    [{}] Deploying stack through the Swarm API", stack_name);
+
+        let content = fs::read_to_string(compose_path)?;
+        let working_dir = compose_path.parent().unwrap_or(Path::new("."));
+        let spec = StackSpec::from_compose(stack_name, &content, working_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to convert compose file of stack '{}': {}", stack_name, e))?;
+        if !spec.unsupported.is_empty() {
+            let unsupported: Vec<&str> = spec.unsupported.iter().map(String::as_str).collect();
+            println!("    [{}] Ignoring unsupported options: {}", stack_name, unsupported.join(", "));
+        }
+
+        self.create_networks(stack_name, &spec).await?;
+        let secret_ids = self.sync_secrets(&spec).await?;
+        let config_ids = self.sync_configs(&spec).await?;
+        if let Some(prepuller) = &self.prepuller {
+            let current = self.current_images(stack_name).await?;
+            prepuller.prepull(stack_name, &spec.services, &current).await?;
+        }
+        let updated = self.sync_services(stack_name, spec.services, &secret_ids, &config_ids).await?;
+
+        self.wait_until_converged(stack_name, DEPLOY_TIMEOUT, &updated).await
+            .map_err(|e| anyhow::anyhow!("Failed to deploy stack: {}", e))?;
+        println!("    [{}] Successfully deployed stack", stack_name);
+        Ok(())
+    }
+
+    /// Crée les réseaux du stack qui n'existent pas encore et vérifie la présence des réseaux externes
+    async fn create_networks(&self, stack_name: &str, spec: &StackSpec) -> Result<()> {
+        let docker = self.client.docker();
+        for name in &spec.external_networks {
+            if docker.inspect_network(name, None).await.is_err() {
+                return Err(anyhow::anyhow!("Network '{}' is declared as external, but could not be found", name));
+            }
+        }
+
+        let options = ListNetworksOptions { filters: Some(Self::namespace_filter(stack_name)) };
+        let existing: Vec<String> = docker.list_networks(Some(options)).await?
+            .into_iter()
+            .filter_map(|network| network.name)
+            .collect();
+
+        for network in &spec.networks {
+            if existing.contains(&network.name) {
+                continue;
+            }
+            println!("    [{}] Creating network {}", stack_name, network.name);
+            let mut network = network.clone();
+            network.driver.get_or_insert_with(|| "overlay".to_string());
+            let name = network.name.clone();
+            docker.create_network(network).await
+                .map_err(|e| anyhow::anyhow!("Failed to create network {}: {}", name, e))?;
+        }
+        Ok(())
+    }
+
+    /// Crée ou met à jour les secrets du stack et retourne l'identifiant de chaque secret référencé (nom -> id).
+    /// Le contenu d'un secret est immuable : le modifier impose de le renommer.
+    async fn sync_secrets(&self, spec: &StackSpec) -> Result<HashMap<String, String>> {
+        let docker = self.client.docker();
+        let existing: HashMap<String, (String, i64)> = docker.list_secrets(None::<ListSecretsOptions>).await?
+            .into_iter()
+            .filter_map(|secret| {
+                let name = secret.spec?.name?;
+                let version = secret.version.and_then(|v| v.index).unwrap_or(0) as i64;
+                Some((name, (secret.id?, version)))
+            })
+            .collect();
+
+        let mut ids = HashMap::new();
+        for name in &spec.external_secrets {
+            let (id, _) = existing.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Secret '{}' is declared as external, but could not be found", name))?;
+            ids.insert(name.clone(), id.clone());
+        }
+        for secret in &spec.secrets {
+            let name = secret.name.clone().unwrap_or_default();
+            let id = match existing.get(&name) {
+                Some((id, version)) => {
+                    docker.update_secret(id, secret.clone(), UpdateSecretOptions { version: *version }).await
+                        .map_err(|e| anyhow::anyhow!("Failed to update secret {} (secrets are immutable, rename it to change its content): {}", name, e))?;
+                    id.clone()
+                }
+                None => docker.create_secret(secret.clone()).await
+                    .map_err(|e| anyhow::anyhow!("Failed to create secret {}: {}", name, e))?
+                    .id,
+            };
+            ids.insert(name, id);
+        }
+        Ok(ids)
+    }
+
+    /// Crée ou met à jour les configs du stack et retourne l'identifiant de chaque config référencée (nom -> id)
+    async fn sync_configs(&self, spec: &StackSpec) -> Result<HashMap<String, String>> {
+        let docker = self.client.docker();
+        let existing: HashMap<String, (String, i64)> = docker.list_configs(None::<ListConfigsOptions>).await?
+            .into_iter()
+            .filter_map(|config| {
+                let name = config.spec?.name?;
+                let version = config.version.and_then(|v| v.index).unwrap_or(0) as i64;
+                Some((name, (config.id?, version)))
+            })
+            .collect();
+
+        let mut ids = HashMap::new();
+        for name in &spec.external_configs {
+            let (id, _) = existing.get(name)
+                .ok_or_else(|| anyhow::anyhow!("Config '{}' is declared as external, but could not be found", name))?;
+            ids.insert(name.clone(), id.clone());
+        }
+        for config in &spec.configs {
+            let name = config.name.clone().unwrap_or_default();
+            let id = match existing.get(&name) {
+                Some((id, version)) => {
+                    docker.update_config(id, config.clone(), UpdateConfigOptions { version: *version }).await
+                        .map_err(|e| anyhow::anyhow!("Failed to update config {} (configs are immutable, rename it to change its content): {}", name, e))?;
+                    id.clone()
+                }
+                None => docker.create_config(config.clone()).await
+                    .map_err(|e| anyhow::anyhow!("Failed to create config {}: {}", name, e))?
+                    .id,
+            };
+            ids.insert(name, id);
+        }
+        Ok(ids)
+    }
+
+    /// Image actuellement déployée de chaque service du stack
+    async fn current_images(&self, stack_name: &str) -> Result<HashMap<String, String>> {
+        let options = ListServicesOptions { filters: Some(Self::namespace_filter(stack_name)), status: None };
+        Ok(self.client.docker().list_services(Some(options)).await?
+            .into_iter()
+            .filter_map(|service| {
+                let spec = service.spec?;
+                let image = spec.task_template?.container_spec?.image?;
+                Some((spec.name?, image))
+            })
+            .collect())
+    }
+
+    /// Crée les nouveaux services, met à jour les existants en conservant leur compteur `force_update` et supprime
+    /// ceux du stack qui ne sont plus dans le compose. Retourne les services mis à jour, dont le statut est à suivre.
+    async fn sync_services(
+        &self,
+        stack_name: &str,
+        services: Vec<ServiceSpec>,
+        secret_ids: &HashMap<String, String>,
+        config_ids: &HashMap<String, String>,
+    ) -> Result<Updated> {
+        let docker = self.client.docker();
+        let options = ListServicesOptions { filters: Some(Self::namespace_filter(stack_name)), status: None };
+        let existing: HashMap<String, _> = docker.list_services(Some(options)).await?
+            .into_iter()
+            .filter_map(|service| Some((service.spec.as_ref()?.name.clone()?, service)))
+            .collect();
+
+        // Services of the stack that are no longer in the compose file are removed, as with `--prune`
+        let names: Vec<String> = services.iter().filter_map(|service| service.name.clone()).collect();
+        for (name, service) in &existing {
+            if names.contains(name) {
+                continue;
+            }
+            println!("    [{}] Removing service {} (no longer in the compose file)", stack_name, name);
+            let id = service.id.as_deref().unwrap_or(name);
+            docker.delete_service(id).await
+                .map_err(|e| anyhow::anyhow!("Failed to remove service {}: {}", name, e))?;
+        }
+
+        let mut updated = Updated::new();
+        for mut service in services {
+            let name = service.name.clone().unwrap_or_default();
+            if let Some(container_spec) = service.task_template.as_mut().and_then(|t| t.container_spec.as_mut()) {
+                for secret in container_spec.secrets.iter_mut().flatten() {
+                    secret.secret_id = secret.secret_name.as_ref().and_then(|n| secret_ids.get(n)).cloned();
+                }
+                for config in container_spec.configs.iter_mut().flatten() {
+                    config.config_id = config.config_name.as_ref().and_then(|n| config_ids.get(n)).cloned();
+                }
+            }
+
+            let credentials = self.service_credentials(&service).await;
+            match existing.get(&name) {
+                Some(current) => {
+                    println!("    [{}] Updating service {}", stack_name, name);
+                    // Keep the counter bumped by force updates, otherwise every deploy would restart the tasks
+                    let force_update = current.spec.as_ref()
+                        .and_then(|s| s.task_template.as_ref())
+                        .and_then(|t| t.force_update);
+                    if let Some(task_template) = service.task_template.as_mut() {
+                        task_template.force_update = force_update;
+                    }
+                    let options = UpdateServiceOptions {
+                        version: current.version.as_ref().and_then(|v| v.index).unwrap_or(0) as i32,
+                        ..Default::default()
+                    };
+                    docker.update_service(&name, service, options, credentials).await
+                        .map_err(|e| anyhow::anyhow!("Failed to update service {}: {}", name, e))?;
+                    updated.insert(name, Self::update_baseline(current));
+                }
+                None => {
+                    println!("    [{}] Creating service {}", stack_name, name);
+                    docker.create_service(service, credentials).await
+                        .map_err(|e| anyhow::anyhow!("Failed to create service {}: {}", name, e))?;
+                }
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Version d'un service et début de sa dernière mise à jour, avant qu'il ne soit mis à jour
+    fn update_baseline(service: &Service) -> (u64, Option<String>) {
+        let version = service.version.as_ref().and_then(|v| v.index).unwrap_or(0);
+        let started_at = service.update_status.as_ref().and_then(|u| u.started_at.as_ref()).map(|d| d.to_string());
+        (version, started_at)
+    }
+
+    /// Force le redémarrage des tâches d'un service du stack, même si sa spécification n'a pas changé
+    pub async fn force_update_service(&self, backend: DeployBackend, stack_name: &str, service_name: &str) -> Result<()> {
+        if backend == DeployBackend::Compose {
+            return self.compose.restart_service(stack_name, service_name).await;
+        }
+        let service = format!("{}_{}", stack_name, service_name);
+        println!("    [{}] Forcing update of service {} (mounted files changed)", stack_name, service);
+
+        let docker = self.client.docker();
+        let current = docker.inspect_service(&service, None).await
+            .map_err(|e| anyhow::anyhow!("Failed to force update of service {}: {}", service, e))?;
+        let updated = Updated::from([(service.clone(), Self::update_baseline(&current))]);
+        let mut spec = current.spec.unwrap_or_default();
+        if let Some(task_template) = spec.task_template.as_mut() {
+            task_template.force_update = Some(task_template.force_update.unwrap_or(0) + 1);
+        }
+        let options = UpdateServiceOptions {
+            version: current.version.and_then(|v| v.index).unwrap_or(0) as i32,
+            ..Default::default()
+        };
+        let credentials = self.service_credentials(&spec).await;
+        docker.update_service(&service, spec, options, credentials).await
+            .map_err(|e| anyhow::anyhow!("Failed to force update of service {}: {}", service, e))?;
+
+        self.wait_until_converged(stack_name, DEPLOY_TIMEOUT, &updated).await
+    }
+
+    /// Déploie une nouvelle version de l'image d'un service Swarm du stack : le service passe à la référence
+    /// épinglée `image` (`tag@digest`)
+    pub async fn update_service_image(&self, stack_name: &str, service_name: &str, image: &str) -> Result<()> {
+        let service = format!("{}_{}", stack_name, service_name);
+        println!("    [{}] Updating service {} to {}", stack_name, service, image);
+
+        let docker = self.client.docker();
+        let current = docker.inspect_service(&service, None).await
+            .map_err(|e| anyhow::anyhow!("Failed to update image of service {}: {}", service, e))?;
+        let updated = Updated::from([(service.clone(), Self::update_baseline(&current))]);
+        let mut spec = current.spec.unwrap_or_default();
+        if let Some(container_spec) = spec.task_template.as_mut().and_then(|t| t.container_spec.as_mut()) {
+            container_spec.image = Some(image.to_string());
+        }
+        let options = UpdateServiceOptions {
+            version: current.version.and_then(|v| v.index).unwrap_or(0) as i32,
+            ..Default::default()
+        };
+        let credentials = self.service_credentials(&spec).await;
+        docker.update_service(&service, spec, options, credentials).await
+            .map_err(|e| anyhow::anyhow!("Failed to update image of service {}: {}", service, e))?;
+
+        self.wait_until_converged(stack_name, DEPLOY_TIMEOUT, &updated).await
+    }
+
+    /// Recrée les conteneurs d'un service d'un projet compose depuis le compose rendu `compose_path`,
+    /// qui référence la nouvelle version de son image, déjà pullée
+    pub async fn recreate_service(&self, stack_name: &str, service_name: &str, compose_path: &Path) -> Result<()> {
+        self.compose.recreate_service(stack_name, service_name, compose_path).await
+    }
+
+    /// Arrête un stack Docker Swarm : supprime ses services, puis ses réseaux, secrets et configs
+    /// une fois les tâches arrêtées (en mode compose, ses conteneurs puis ses réseaux). Les volumes sont conservés.
+    pub async fn stop_stack(&self, backend: DeployBackend, stack_name: &str) -> Result<()> {
+        println!("    [{}] Removing stack", stack_name);
+        let result = match backend {
+            DeployBackend::Swarm => self.remove_stack(stack_name).await,
+            DeployBackend::Compose => self.compose.remove_project(stack_name).await,
+        };
+        if let Err(e) = result {
             // Don't return error here as the stack might not exist
+            println!("    [{}] Warning: Error stopping stack: {}", stack_name, e);
+        } else {
+            println!("    [{}] Successfully stopped stack", stack_name);
         }
-        
         Ok(())
     }
-}
 
+    async fn remove_stack(&self, stack_name: &str) -> Result<()> {
+        let docker = self.client.docker();
+        let filters = Self::namespace_filter(stack_name);
+
+        let services = docker.list_services(Some(ListServicesOptions { filters: Some(filters.clone()), status: None })).await?;
+        for service in services {
+            if let Some(id) = service.id {
+                docker.delete_service(&id).await?;
+            }
+        }
+
+        // Networks, secrets and configs stay in use until the tasks are gone
+        let deadline = Instant::now() + REMOVE_TIMEOUT;
+        while !docker.list_tasks(Some(ListTasksOptions { filters: Some(filters.clone()) })).await?.is_empty() {
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!("tasks still running after {}s", REMOVE_TIMEOUT.as_secs()));
+            }
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+
+        for network in docker.list_networks(Some(ListNetworksOptions { filters: Some(filters.clone()) })).await? {
+            if let Some(id) = network.id {
+                docker.remove_network(&id).await?;
+            }
+        }
+        for secret in docker.list_secrets(Some(ListSecretsOptions { filters: Some(filters.clone()) })).await? {
+            if let Some(id) = secret.id {
+                docker.delete_secret(&id).await?;
+            }
+        }
+        for config in docker.list_configs(Some(ListConfigsOptions { filters: Some(filters) })).await? {
+            if let Some(id) = config.id {
+                docker.delete_config(&id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attend que tous les services d'un stack aient leurs tâches en cours d'exécution.
+    /// Swarm ne passe une tâche à l'état running qu'une fois son healthcheck réussi.
+    pub async fn wait_until_healthy(&self, backend: DeployBackend, stack_name: &str, timeout: Duration) -> Result<()> {
+        if backend == DeployBackend::Compose {
+            return self.compose.wait_until_healthy(stack_name, timeout).await;
+        }
+        self.wait_until_converged(stack_name, timeout, &Updated::new()).await
+    }
+
+    /// Attend la convergence des services d'un stack Swarm ; le statut de mise à jour n'est suivi que pour
+    /// les services de `updated`
+    async fn wait_until_converged(&self, stack_name: &str, timeout: Duration, updated: &Updated) -> Result<()> {
+        println!("    [{}] Waiting for stack to become healthy (timeout {}s)", stack_name, timeout.as_secs());
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let pending = self.pending_services(stack_name, updated).await?;
+            if pending.is_empty() {
+                println!("    [{}] Stack is healthy", stack_name);
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow::anyhow!(
+                    "Stack '{}' not healthy after {}s (waiting for: {})",
+                    stack_name,
+                    timeout.as_secs(),
+                    pending.join(", ")
+                ));
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        }
+    }
+
+    /// Liste les services d'un stack dont toutes les tâches souhaitées ne tournent pas encore
+    /// (ou dont la mise à jour est en cours) ; une mise à jour suspendue ou annulée est une erreur.
+    /// Le statut de mise à jour n'est lu que pour une mise à jour de `updated` qui a changé la version du service
+    /// et démarré une nouvelle mise à jour : un statut laissé par une mise à jour précédente est ignoré.
+    async fn pending_services(&self, stack_name: &str, updated: &Updated) -> Result<Vec<String>> {
+        let options = ListServicesOptions {
+            filters: Some(Self::namespace_filter(stack_name)),
+            status: Some(true),
+        };
+
+        let services = self.client.docker().list_services(Some(options)).await?;
+        if services.is_empty() {
+            return Ok(vec![format!("no services found for stack '{}'", stack_name)]);
+        }
+
+        let mut pending = Vec::new();
+        for service in services {
+            let name = service.spec.as_ref().and_then(|spec| spec.name.clone()).unwrap_or_default();
+            let (version, started_at) = Self::update_baseline(&service);
+            let update = service.update_status.as_ref().filter(|_| {
+                updated.get(&name).is_some_and(|(previous_version, previous_start)| {
+                    version > *previous_version && started_at != *previous_start
+                })
+            });
+            match update.and_then(|u| u.state) {
+                Some(state @ (ServiceUpdateStatusStateEnum::PAUSED
+                    | ServiceUpdateStatusStateEnum::ROLLBACK_PAUSED
+                    | ServiceUpdateStatusStateEnum::ROLLBACK_COMPLETED)) => {
+                    return Err(anyhow::anyhow!(
+                        "Update of service {} {}: {}",
+                        name,
+                        state,
+                        update.and_then(|u| u.message.clone()).unwrap_or_default()
+                    ));
+                }
+                Some(ServiceUpdateStatusStateEnum::UPDATING | ServiceUpdateStatusStateEnum::ROLLBACK_STARTED) => {
+                    pending.push(name);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let status = service.service_status.as_ref();
+            let running = status.and_then(|s| s.running_tasks).unwrap_or(0);
+            let completed = status.and_then(|s| s.completed_tasks).unwrap_or(0);
+            let desired = status.and_then(|s| s.desired_tasks).unwrap_or(0);
+            // Job services converge once their tasks have completed
+            if running + completed < desired {
+                pending.push(name);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Filtre sur le label de namespace du stack
+    fn namespace_filter(stack_name: &str) -> HashMap<String, Vec<String>> {
+        let mut filters = HashMap::new();
+        filters.insert("label".to_string(), vec![format!("{}={}", NAMESPACE_LABEL, stack_name)]);
+        filters
+    }
+}
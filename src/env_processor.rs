@@ -0,0 +1,364 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use serde_yaml::Value;
+use crate::overlay_processor::OverlayProcessor;
+
+/// Processeur pour l'interpolation des variables (`${VAR}`, `${VAR:-défaut}`...) dans les fichiers compose
+pub struct EnvProcessor;
+
+impl EnvProcessor {
+    /// Construit les variables disponibles pour un stack, par priorité croissante :
+    /// `.env` du repository, `.env` du stack (avec leurs overlays d'environnement),
+    /// puis variables d'environnement de DockerOps autorisées par `allowlist`.
+    pub fn load_variables(
+        repo_path: &Path,
+        stack_dir: &Path,
+        environment: Option<&str>,
+        allowlist: &[String],
+    ) -> Result<HashMap<String, String>> {
+        let mut variables = HashMap::new();
+
+        for dir in [repo_path, stack_dir] {
+            let base = dir.join(".env");
+            let files = base.is_file().then_some(base).into_iter()
+                .chain(OverlayProcessor::overlay_files(dir, ".env", environment));
+            for path in files {
+                let content = fs::read_to_string(&path)?;
+                Self::parse_env_file(&content, &mut variables)
+                    .map_err(|e| anyhow::anyhow!("Invalid env file {}: {}", path.display(), e))?;
+            }
+        }
+
+        for (name, value) in std::env::vars() {
+            if Self::is_allowed(&name, allowlist) {
+                variables.insert(name, value);
+            }
+        }
+
+        Ok(variables)
+    }
+
+    /// Interpole les valeurs d'un contenu compose. Retourne le contenu (inchangé s'il n'y a rien à interpoler)
+    /// et les variables non définies remplacées par une chaîne vide ; échoue si une variable obligatoire
+    /// (`${VAR:?message}`) manque ou si une expression est invalide.
+    pub fn interpolate(content: &str, variables: &HashMap<String, String>) -> Result<(String, BTreeSet<String>)> {
+        if !content.contains('$') {
+            return Ok((content.to_string(), BTreeSet::new()));
+        }
+
+        let mut yaml_value: Value = serde_yaml::from_str(content)?;
+        let mut unset = BTreeSet::new();
+        let mut errors = Vec::new();
+        let mut path = Vec::new();
+        let changed = Self::interpolate_value(&mut yaml_value, variables, &mut path, &mut unset, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Unresolved variables:\n  - {}", errors.join("\n  - ")));
+        }
+        if !changed {
+            return Ok((content.to_string(), unset));
+        }
+
+        Ok((serde_yaml::to_string(&yaml_value)?, unset))
+    }
+
+    /// Interpole récursivement les chaînes d'une valeur YAML ; retourne true si une chaîne a changé
+    fn interpolate_value(
+        value: &mut Value,
+        variables: &HashMap<String, String>,
+        path: &mut Vec<String>,
+        unset: &mut BTreeSet<String>,
+        errors: &mut Vec<String>,
+    ) -> bool {
+        match value {
+            Value::String(s) if s.contains('$') => match Self::interpolate_str(s, variables, unset) {
+                Ok(interpolated) => {
+                    // Keep literal dollars escaped as in Compose files; StackSpec unescapes them on deploy
+                    let escaped = interpolated.replace('$', "$$");
+                    let changed = escaped != *s;
+                    *s = escaped;
+                    changed
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", path.join("."), e));
+                    false
+                }
+            },
+            Value::Mapping(mapping) => {
+                let mut changed = false;
+                for (key, val) in mapping.iter_mut() {
+                    path.push(key.as_str().unwrap_or_default().to_string());
+                    changed |= Self::interpolate_value(val, variables, path, unset, errors);
+                    path.pop();
+                }
+                changed
+            }
+            Value::Sequence(sequence) => {
+                let mut changed = false;
+                for (index, item) in sequence.iter_mut().enumerate() {
+                    path.push(index.to_string());
+                    changed |= Self::interpolate_value(item, variables, path, unset, errors);
+                    path.pop();
+                }
+                changed
+            }
+            Value::Tagged(tagged) => Self::interpolate_value(&mut tagged.value, variables, path, unset, errors),
+            _ => false,
+        }
+    }
+
+    /// Interpole une chaîne : `$$`, `$VAR`, `${VAR}`, `${VAR:-défaut}`, `${VAR-défaut}`,
+    /// `${VAR:?erreur}`, `${VAR?erreur}`, `${VAR:+alternative}`, `${VAR+alternative}`
+    fn interpolate_str(
+        s: &str,
+        variables: &HashMap<String, String>,
+        unset: &mut BTreeSet<String>,
+    ) -> std::result::Result<String, String> {
+        let mut result = String::with_capacity(s.len());
+        let mut rest = s;
+
+        while let Some(index) = rest.find('$') {
+            result.push_str(&rest[..index]);
+            let after = &rest[index + 1..];
+
+            if let Some(tail) = after.strip_prefix('$') {
+                result.push('$');
+                rest = tail;
+            } else if let Some(braced) = after.strip_prefix('{') {
+                let end = Self::closing_brace(braced)
+                    .ok_or_else(|| format!("unterminated expression in '{}'", s))?;
+                result.push_str(&Self::evaluate(&braced[..end], variables, unset)?);
+                rest = &braced[end + 1..];
+            } else {
+                let name_len = Self::name_length(after);
+                if name_len == 0 {
+                    // A lone '$' is kept as is
+                    result.push('$');
+                } else {
+                    result.push_str(&Self::lookup(&after[..name_len], variables, unset));
+                }
+                rest = &after[name_len..];
+            }
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    /// Évalue le contenu de `${...}`
+    fn evaluate(
+        expression: &str,
+        variables: &HashMap<String, String>,
+        unset: &mut BTreeSet<String>,
+    ) -> std::result::Result<String, String> {
+        let name_len = Self::name_length(expression);
+        let (name, modifier) = expression.split_at(name_len);
+        if name.is_empty() {
+            return Err(format!("invalid interpolation format for '${{{}}}'", expression));
+        }
+
+        let value = variables.get(name);
+        let is_set = value.is_some();
+        let is_non_empty = value.is_some_and(|v| !v.is_empty());
+
+        let (operator, word) = ([":-", ":?", ":+", "-", "?", "+"].iter())
+            .find_map(|op| modifier.strip_prefix(op).map(|word| (*op, word)))
+            .unwrap_or(("", modifier));
+
+        match operator {
+            "" if word.is_empty() => Ok(Self::lookup(name, variables, unset)),
+            ":-" if !is_non_empty => Self::interpolate_str(word, variables, unset),
+            "-" if !is_set => Self::interpolate_str(word, variables, unset),
+            ":?" | "?" if !is_non_empty && (operator == ":?" || !is_set) => {
+                let message = Self::interpolate_str(word, variables, unset)?;
+                if message.is_empty() {
+                    Err(format!("required variable {} is missing a value", name))
+                } else {
+                    Err(format!("required variable {} is missing a value: {}", name, message))
+                }
+            }
+            ":+" if is_non_empty => Self::interpolate_str(word, variables, unset),
+            "+" if is_set => Self::interpolate_str(word, variables, unset),
+            ":+" | "+" => Ok(String::new()),
+            ":-" | "-" | ":?" | "?" => Ok(value.cloned().unwrap_or_default()),
+            _ => Err(format!("invalid interpolation format for '${{{}}}'", expression)),
+        }
+    }
+
+    fn lookup(name: &str, variables: &HashMap<String, String>, unset: &mut BTreeSet<String>) -> String {
+        match variables.get(name) {
+            Some(value) => value.clone(),
+            None => {
+                unset.insert(name.to_string());
+                String::new()
+            }
+        }
+    }
+
+    /// Longueur du nom de variable en tête de `s` (`[A-Za-z_][A-Za-z0-9_]*`)
+    fn name_length(s: &str) -> usize {
+        if !s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            return 0;
+        }
+        s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(s.len())
+    }
+
+    /// Position de l'accolade fermante correspondante, en tenant compte des `${...}` imbriqués
+    fn closing_brace(s: &str) -> Option<usize> {
+        let mut depth = 0;
+        for (index, c) in s.char_indices() {
+            match c {
+                '{' if index > 0 && s[..index].ends_with('$') => depth += 1,
+                '}' if depth == 0 => return Some(index),
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Lit un fichier `.env` : `KEY=valeur`, préfixe `export` optionnel, commentaires `#`,
+    /// valeurs entre guillemets simples (littérales) ou doubles (échappements et interpolation)
+    pub fn parse_env_file(content: &str, variables: &mut HashMap<String, String>) -> std::result::Result<(), String> {
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let Some((name, raw)) = line.split_once('=') else {
+                continue;
+            };
+            let name = name.trim();
+            if name.is_empty() || Self::name_length(name) != name.len() {
+                return Err(format!("line {}: invalid variable name '{}'", number + 1, name));
+            }
+
+            let raw = raw.trim();
+            let value = if let Some(quoted) = raw.strip_prefix('\'') {
+                quoted.split_once('\'').map(|(value, _)| value.to_string())
+                    .ok_or_else(|| format!("line {}: unterminated quote", number + 1))?
+            } else if let Some(quoted) = raw.strip_prefix('"') {
+                let end = quoted.char_indices()
+                    .find(|(i, c)| *c == '"' && !quoted[..*i].ends_with('\\'))
+                    .map(|(i, _)| i)
+                    .ok_or_else(|| format!("line {}: unterminated quote", number + 1))?;
+                let unescaped = quoted[..end]
+                    .replace("\\n", "\n")
+                    .replace("\\t", "\t")
+                    .replace("\\\"", "\"")
+                    .replace("\\\\", "\\");
+                Self::interpolate_str(&unescaped, variables, &mut BTreeSet::new())
+                    .map_err(|e| format!("line {}: {}", number + 1, e))?
+            } else {
+                let unquoted = raw.split(" #").next().unwrap_or_default().trim_end();
+                Self::interpolate_str(unquoted, variables, &mut BTreeSet::new())
+                    .map_err(|e| format!("line {}: {}", number + 1, e))?
+            };
+
+            variables.insert(name.to_string(), value);
+        }
+
+        Ok(())
+    }
+
+    /// Indique si une variable d'environnement de DockerOps peut être utilisée (`NAME` ou préfixe `APP_*`)
+    fn is_allowed(name: &str, allowlist: &[String]) -> bool {
+        allowlist.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    fn interpolate(s: &str, variables: &HashMap<String, String>) -> std::result::Result<String, String> {
+        EnvProcessor::interpolate_str(s, variables, &mut BTreeSet::new())
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let vars = variables(&[("TAG", "1.27"), ("EMPTY", "")]);
+        assert_eq!(interpolate("nginx:${TAG}", &vars).unwrap(), "nginx:1.27");
+        assert_eq!(interpolate("nginx:$TAG-alpine", &vars).unwrap(), "nginx:1.27-alpine");
+
+        let mut unset = BTreeSet::new();
+        assert_eq!(EnvProcessor::interpolate_str("a${MISSING}b", &vars, &mut unset).unwrap(), "ab");
+        assert!(unset.contains("MISSING"));
+    }
+
+    #[test]
+    fn applies_defaults() {
+        let vars = variables(&[("EMPTY", "")]);
+        assert_eq!(interpolate("${EMPTY:-x}", &vars).unwrap(), "x");
+        assert_eq!(interpolate("${EMPTY-x}", &vars).unwrap(), "");
+        assert_eq!(interpolate("${MISSING:-x}", &vars).unwrap(), "x");
+        assert_eq!(interpolate("${MISSING-x}", &vars).unwrap(), "x");
+        assert_eq!(interpolate("${MISSING:-${EMPTY:-nested}}", &vars).unwrap(), "nested");
+    }
+
+    #[test]
+    fn rejects_missing_required_variables() {
+        let vars = variables(&[("EMPTY", ""), ("SET", "v")]);
+        assert_eq!(interpolate("${SET:?required}", &vars).unwrap(), "v");
+        assert_eq!(interpolate("${EMPTY?required}", &vars).unwrap(), "");
+
+        let error = interpolate("${EMPTY:?set DB_PASSWORD}", &vars).unwrap_err();
+        assert!(error.contains("EMPTY") && error.contains("set DB_PASSWORD"), "{}", error);
+        assert!(interpolate("${MISSING:?}", &vars).unwrap_err().contains("MISSING"));
+        assert!(interpolate("${MISSING?}", &vars).is_err());
+    }
+
+    #[test]
+    fn keeps_escaped_dollars() {
+        let vars = variables(&[("VAR", "v")]);
+        assert_eq!(interpolate("$$VAR", &vars).unwrap(), "$VAR");
+        assert_eq!(interpolate("cost: 5$", &vars).unwrap(), "cost: 5$");
+
+        // Interpolated documents keep literal dollars escaped for StackSpec
+        let (content, _) = EnvProcessor::interpolate("x: \"$$HOME ${VAR}\"\n", &vars).unwrap();
+        assert_eq!(serde_yaml::from_str::<Value>(&content).unwrap()["x"], Value::String("$$HOME v".to_string()));
+    }
+
+    #[test]
+    fn reports_the_path_of_missing_required_variables() {
+        let content = "services:\n  web:\n    environment:\n      DB_PASSWORD: ${DB_PASSWORD:?}\n";
+        let error = EnvProcessor::interpolate(content, &HashMap::new()).unwrap_err().to_string();
+        assert!(error.contains("services.web.environment.DB_PASSWORD"), "{}", error);
+    }
+
+    #[test]
+    fn parses_env_files() {
+        let content = "\
+# comment
+export PLAIN=value # trailing comment
+SINGLE='literal $PLAIN \\n'
+DOUBLE=\"line\\nnext ${PLAIN}\"
+QUOTE=\"say \\\"hi\\\"\"
+EMPTY=
+";
+        let mut vars = HashMap::new();
+        EnvProcessor::parse_env_file(content, &mut vars).unwrap();
+        assert_eq!(vars["PLAIN"], "value");
+        assert_eq!(vars["SINGLE"], "literal $PLAIN \\n");
+        assert_eq!(vars["DOUBLE"], "line\nnext value");
+        assert_eq!(vars["QUOTE"], "say \"hi\"");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn rejects_invalid_env_files() {
+        assert!(EnvProcessor::parse_env_file("1BAD=x", &mut HashMap::new()).is_err());
+        assert!(EnvProcessor::parse_env_file("OPEN='x", &mut HashMap::new()).is_err());
+        assert!(EnvProcessor::parse_env_file("OPEN=\"x", &mut HashMap::new()).is_err());
+    }
+}
@@ -24,6 +24,7 @@ impl GitHubClient {
     }
 
     /// Crée un nouveau client GitHub avec un token spécifique
+    #[allow(dead_code)]
     pub fn with_token(token: String) -> Result<Self> {
         let octocrab = Octocrab::builder()
             .personal_token(token)
@@ -40,6 +41,7 @@ impl GitHubClient {
     }
 }
 
+#[allow(dead_code)]
 impl Default for GitHubClient {
     fn default() -> Self {
         Self::new().unwrap_or_else(|_| {
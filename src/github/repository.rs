@@ -1,8 +1,12 @@
 use anyhow::Result;
 use git2::{FetchOptions, RemoteCallbacks, build::RepoBuilder};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use crate::github::client::GitHubClient;
 
+/// Compteur pour nommer les répertoires de clone de façon unique
+static CLONE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
 /// Service pour gérer les opérations sur les repositories GitHub
 pub struct RepositoryService {
     client: GitHubClient,
@@ -25,43 +29,75 @@ impl RepositoryService {
             github_url.to_string()
         };
 
-        // Create temporary directory for cloning
+        // Create temporary directory for cloning (unique even when several clones run in parallel)
+        let unique_suffix = format!("{}_{}", chrono::Utc::now().timestamp(), CLONE_COUNTER.fetch_add(1, Ordering::Relaxed));
         let temp_dir = if cfg!(windows) {
-            format!("{}\\temp_repo_{}", std::env::var("TEMP").unwrap_or_else(|_| "C:\\temp".to_string()), chrono::Utc::now().timestamp())
+            format!("{}\\temp_repo_{}", std::env::var("TEMP").unwrap_or_else(|_| "C:\\temp".to_string()), unique_suffix)
         } else {
-            format!("/tmp/temp_repo_{}", chrono::Utc::now().timestamp())
+            format!("/tmp/temp_repo_{}", unique_suffix)
         };
-        let repo_path = Path::new(&temp_dir);
 
         println!("Cloning repository from: {}", clone_url);
 
-        // Setup authentication if token is available
-        let mut callbacks = RemoteCallbacks::new();
-
-        if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        let token = std::env::var("GITHUB_TOKEN").ok();
+        if token.is_some() {
             println!("Using GitHub token for authentication");
-            let token_clone = token.clone();
-            callbacks.credentials(move |_url, username_from_url, _allowed_types| {
-                git2::Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &token_clone)
-            });
         } else {
             println!("No GitHub token found. Trying to clone without authentication...");
             println!("If this fails, set the GITHUB_TOKEN environment variable");
         }
 
-        let mut fetch_options = FetchOptions::new();
-        fetch_options.remote_callbacks(callbacks);
+        // git2 is blocking: run the clone off the async runtime so that other repositories progress meanwhile
+        let repo_path = temp_dir.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            // Setup authentication if token is available
+            let mut callbacks = RemoteCallbacks::new();
+            if let Some(token) = token {
+                callbacks.credentials(move |_url, username_from_url, _allowed_types| {
+                    git2::Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &token)
+                });
+            }
+
+            let mut fetch_options = FetchOptions::new();
+            fetch_options.remote_callbacks(callbacks);
 
-        let mut builder = RepoBuilder::new();
-        builder.fetch_options(fetch_options);
+            let mut builder = RepoBuilder::new();
+            builder.fetch_options(fetch_options);
 
-        let _repo = builder.clone(&clone_url, repo_path)
-            .map_err(|e| anyhow::anyhow!("Failed to clone repository: {}", e))?;
+            builder.clone(&clone_url, Path::new(&repo_path))
+                .map_err(|e| anyhow::anyhow!("Failed to clone repository: {}", e))?;
+            Ok(())
+        })
+        .await??;
 
         Ok(temp_dir)
     }
 
+    /// Place un repository cloné sur une révision donnée (SHA, tag ou branche) et retourne le SHA du commit
+    pub fn checkout_revision(repo_path: &str, revision: &str) -> Result<String> {
+        let repo = git2::Repository::open(repo_path)?;
+
+        // Branches only exist as remote-tracking refs in a fresh clone
+        let object = repo.revparse_single(revision)
+            .or_else(|_| repo.revparse_single(&format!("origin/{}", revision)))
+            .map_err(|e| anyhow::anyhow!("Revision '{}' not found: {}", revision, e))?;
+        let commit = object.peel_to_commit()?;
+
+        repo.checkout_tree(commit.as_object(), Some(git2::build::CheckoutBuilder::new().force()))?;
+        repo.set_head_detached(commit.id())?;
+
+        Ok(commit.id().to_string())
+    }
+
+    /// Retourne le SHA du commit HEAD d'un repository cloné
+    pub fn head_commit(repo_path: &str) -> Option<String> {
+        let repo = git2::Repository::open(repo_path).ok()?;
+        let commit = repo.head().ok()?.peel_to_commit().ok()?;
+        Some(commit.id().to_string())
+    }
+
     /// Vérifie si un repository existe et est accessible via l'API GitHub
+    #[allow(dead_code)]
     pub async fn check_repository(&self, github_url: &str) -> Result<bool> {
         // Parse GitHub URL to extract owner and repo
         let (owner, repo) = self.parse_github_url(github_url)?;
@@ -90,4 +126,3 @@ impl RepositoryService {
         }
     }
 }
-
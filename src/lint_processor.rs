@@ -0,0 +1,729 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_yaml::Value;
+use crate::compose_processor::ComposeProcessor;
+use crate::config::DiscoveryConfig;
+use crate::dependency_graph::DependencyGraph;
+use crate::lock_processor::{LockProcessor, LOCK_FILE};
+use crate::manifest_processor::{ManifestProcessor, MANIFEST_FILE};
+use crate::models::{Manifest, MANIFEST_API_VERSION};
+use crate::overlay_processor::OverlayProcessor;
+use crate::stack_processor::StackProcessor;
+use crate::template_processor::TemplateProcessor;
+
+/// Problème détecté dans un fichier du repository
+pub struct LintIssue {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file.display(), self.line, self.column, self.message)
+    }
+}
+
+/// Résultat de la validation d'un repository
+#[derive(Default)]
+pub struct LintReport {
+    pub checked_files: BTreeSet<PathBuf>,
+    pub issues: Vec<LintIssue>,
+}
+
+/// Processeur pour la validation des manifestes et fichiers compose d'un repository (`lint`, pré-vérification
+/// avant déploiement). Chaque fichier est validé séparément pour localiser les problèmes ; les templates
+/// `.tmpl` sont validés après rendu.
+pub struct LintProcessor;
+
+impl LintProcessor {
+    /// Valide tous les manifestes du repository, puis les fichiers compose des stacks activés
+    pub fn lint(repo_path: &Path, environment: Option<&str>, discovery: Option<&DiscoveryConfig>) -> LintReport {
+        let mut report = LintReport::default();
+
+        let Some(manifest) = Self::lint_manifests(repo_path, environment, discovery, &mut report) else {
+            return report;
+        };
+
+        Self::lint_lock_file(repo_path, &mut report);
+
+        let values = Self::lint_values(repo_path, environment, &mut report);
+        for stack_def in manifest.stacks.iter().filter(|s| s.enabled) {
+            let Ok(stack_dir) = StackProcessor::stack_directory(&repo_path.to_string_lossy(), stack_def) else {
+                continue;
+            };
+            // A missing stack directory is skipped at deploy time, not an error
+            if !stack_dir.is_dir() {
+                continue;
+            }
+
+            if stack_def.secrets.is_none() {
+                let secrets_path = stack_dir.join("secrets.yaml");
+                if secrets_path.is_file() {
+                    report.check_file(&secrets_path, &SECRETS_FILE, false);
+                }
+            }
+
+            let stack_values = Self::lint_values(&stack_dir, environment, &mut report);
+            let (Some(values), Some(stack_values)) = (&values, stack_values) else {
+                continue;
+            };
+            let values = TemplateProcessor::merge_values(values.clone(), stack_values);
+            let context = TemplateProcessor::context(&values, environment, Some(stack_def.swarm_name()));
+
+            let compose_files = match StackProcessor::compose_files(&stack_dir, stack_def, environment) {
+                Ok(files) => files,
+                Err(e) => {
+                    report.push(&Self::manifest_path(repo_path), 1, 1, e.to_string());
+                    continue;
+                }
+            };
+            for path in compose_files {
+                Self::lint_compose_file(&path, &stack_dir, repo_path, &context, &mut report);
+            }
+        }
+
+        report
+    }
+
+    /// Valide dockerops.yaml ou les fichiers historiques (overlays compris), puis leur fusion
+    fn lint_manifests(
+        repo_path: &Path,
+        environment: Option<&str>,
+        discovery: Option<&DiscoveryConfig>,
+        report: &mut LintReport,
+    ) -> Option<Manifest> {
+        let manifest_files = Self::with_overlays(repo_path, MANIFEST_FILE, environment);
+        let files: Vec<(PathBuf, &Schema, bool)> = if manifest_files.is_empty() {
+            [("stacks.yaml", &STACKS_FILE), ("volumes.yaml", &VOLUMES_FILE), ("nfs.yaml", &NFS)]
+                .into_iter()
+                .flat_map(|(name, schema)| {
+                    Self::with_overlays(repo_path, name, environment).into_iter().enumerate()
+                        .map(move |(index, path)| (path, schema, index > 0 || !repo_path.join(name).is_file()))
+                })
+                .collect()
+        } else {
+            let base_exists = repo_path.join(MANIFEST_FILE).is_file();
+            manifest_files.into_iter().enumerate()
+                .map(|(index, path)| (path, &MANIFEST, index > 0 || !base_exists))
+                .collect()
+        };
+
+        // Overlays only need to be valid once merged into their base file
+        for (path, schema, partial) in &files {
+            report.check_file(path, schema, *partial);
+        }
+        if !report.issues.is_empty() {
+            return None;
+        }
+
+        let manifest_path = Self::manifest_path(repo_path);
+        let manifest = match ManifestProcessor::load(repo_path, environment, discovery) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                report.push(&manifest_path, 1, 1, format!("{:#}", e));
+                return None;
+            }
+        };
+
+        // Cross-entry checks, located in the base file when the entry comes from it
+        let content = fs::read_to_string(&manifest_path).unwrap_or_default();
+        let prefix: Vec<Segment> = if manifest_path.ends_with(MANIFEST_FILE) {
+            vec![Segment::Key("stacks".to_string())]
+        } else {
+            Vec::new()
+        };
+        let mut seen = HashSet::new();
+        for (index, stack_def) in manifest.stacks.iter().enumerate() {
+            let mut path = prefix.clone();
+            path.push(Segment::Index(index));
+            if !seen.insert(stack_def.swarm_name()) {
+                let (line, column) = locate(&content, &path);
+                report.push(&manifest_path, line, column, format!("stack name '{}' is used by several entries", stack_def.swarm_name()));
+            }
+            if let Err(e) = StackProcessor::stack_directory(&repo_path.to_string_lossy(), stack_def) {
+                path.push(Segment::Key("path".to_string()));
+                let (line, column) = locate(&content, &path);
+                report.push(&manifest_path, line, column, e.to_string());
+            }
+        }
+        if let Err(e) = DependencyGraph::from_definitions(&manifest.stacks).and_then(|graph| graph.levels()) {
+            let (line, column) = locate(&content, &prefix);
+            report.push(&manifest_path, line, column, e.to_string());
+        }
+
+        report.issues.is_empty().then_some(manifest)
+    }
+
+    /// Valide dockerops.lock : schéma, puis format des digests
+    fn lint_lock_file(repo_path: &Path, report: &mut LintReport) {
+        let path = repo_path.join(LOCK_FILE);
+        if !path.is_file() || report.check_file(&path, &LOCK, false).is_none() {
+            return;
+        }
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        if let Err(e) = LockProcessor::parse(&content) {
+            report.push(&path, 1, 1, e.to_string());
+        }
+    }
+
+    /// Valide un fichier compose (rendu s'il s'agit d'un template) et les fichiers qu'il inclut
+    fn lint_compose_file(path: &Path, stack_dir: &Path, repo_path: &Path, context: &Value, report: &mut LintReport) {
+        let content = match TemplateProcessor::read(path, context) {
+            Ok(content) => content,
+            Err(e) => {
+                report.push(path, 1, 1, e.to_string());
+                return;
+            }
+        };
+        report.checked_files.insert(path.to_path_buf());
+        if report.check_content(path, &content, &COMPOSE, false).is_none() {
+            return;
+        }
+
+        let file_dir = path.parent().unwrap_or(stack_dir);
+        match ComposeProcessor::resolve_includes_and_extends(&content, file_dir, repo_path) {
+            Ok((_, included_files)) => {
+                for included in included_files {
+                    if !report.checked_files.contains(&included) {
+                        report.check_file(&included, &COMPOSE, false);
+                    }
+                }
+            }
+            Err(e) => report.push(path, 1, 1, format!("{:#}", e)),
+        }
+    }
+
+    /// Valide la syntaxe de `values.yaml` et de ses overlays, puis retourne les valeurs fusionnées
+    fn lint_values(dir: &Path, environment: Option<&str>, report: &mut LintReport) -> Option<Value> {
+        let issues = report.issues.len();
+        for path in Self::with_overlays(dir, "values.yaml", environment) {
+            report.check_file(&path, &Schema::Any, false);
+        }
+        if report.issues.len() > issues {
+            return None;
+        }
+        TemplateProcessor::load_values(dir, environment).ok()
+    }
+
+    /// Un fichier de configuration (s'il existe) suivi de ses overlays d'environnement
+    fn with_overlays(dir: &Path, file_name: &str, environment: Option<&str>) -> Vec<PathBuf> {
+        let base = dir.join(file_name);
+        base.is_file().then_some(base).into_iter()
+            .chain(OverlayProcessor::overlay_files(dir, file_name, environment))
+            .collect()
+    }
+
+    /// Manifeste principal du repository, auquel sont rattachés les problèmes de configuration des stacks
+    fn manifest_path(repo_path: &Path) -> PathBuf {
+        let manifest = repo_path.join(MANIFEST_FILE);
+        if manifest.is_file() {
+            manifest
+        } else {
+            repo_path.join("stacks.yaml")
+        }
+    }
+}
+
+impl LintReport {
+    fn push(&mut self, file: &Path, line: usize, column: usize, message: String) {
+        self.issues.push(LintIssue { file: file.to_path_buf(), line, column, message });
+    }
+
+    fn check_file(&mut self, path: &Path, schema: &Schema, partial: bool) -> Option<Value> {
+        self.checked_files.insert(path.to_path_buf());
+        match fs::read_to_string(path) {
+            Ok(content) => self.check_content(path, &content, schema, partial),
+            Err(e) => {
+                self.push(path, 1, 1, format!("cannot read file: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Vérifie la syntaxe YAML puis le schéma d'un contenu. Avec `partial` (overlays), les champs
+    /// obligatoires peuvent être absents.
+    fn check_content(&mut self, path: &Path, content: &str, schema: &Schema, partial: bool) -> Option<Value> {
+        let value: Value = match serde_yaml::from_str(content) {
+            Ok(value) => value,
+            Err(e) => {
+                let (line, column) = e.location().map(|l| (l.line(), l.column())).unwrap_or((1, 1));
+                let message = e.to_string();
+                let message = message.split(" at line ").next().unwrap_or_default();
+                self.push(path, line, column, format!("invalid YAML: {}", message));
+                return None;
+            }
+        };
+
+        let issues = self.issues.len();
+        let mut checker = Checker { file: path, content, partial, report: self };
+        checker.check(&value, schema, &mut Vec::new());
+        (self.issues.len() == issues).then_some(value)
+    }
+}
+
+/// Élément du chemin d'une valeur dans un document YAML
+#[derive(Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn display_path(path: &[Segment]) -> String {
+    let mut display = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if display.is_empty() => display.push_str(key),
+            Segment::Key(key) => display.push_str(&format!(".{}", key)),
+            Segment::Index(index) => display.push_str(&format!("[{}]", index)),
+        }
+    }
+    if display.is_empty() {
+        display.push_str("document");
+    }
+    display
+}
+
+/// Schéma attendu pour une valeur YAML. Les booléens des fichiers compose sont des `Scalar`,
+/// car ils peuvent contenir une variable avant interpolation.
+enum Schema {
+    Any,
+    /// Chaîne, nombre ou booléen
+    Scalar,
+    Bool,
+    Enum(&'static [&'static str]),
+    List(&'static Schema),
+    /// Mapping aux clés libres
+    Dict(&'static Schema),
+    /// Mapping aux champs connus ; `required` accepte des alternatives (`secret|id`),
+    /// `extensions` autorise les clés `x-*`
+    Object {
+        fields: &'static [(&'static str, Schema)],
+        required: &'static [&'static str],
+        extensions: bool,
+    },
+    OneOf(&'static [Schema]),
+}
+
+impl Schema {
+    fn accepts_kind(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Schema::Any, _) => true,
+            (Schema::Scalar, Value::String(_) | Value::Number(_) | Value::Bool(_)) => true,
+            (Schema::Bool, Value::Bool(_)) => true,
+            (Schema::Enum(_), Value::String(_)) => true,
+            (Schema::List(_), Value::Sequence(_)) => true,
+            (Schema::Dict(_) | Schema::Object { .. }, Value::Mapping(_)) => true,
+            (Schema::OneOf(options), value) => options.iter().any(|option| option.accepts_kind(value)),
+            _ => false,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Schema::Any => "any value".to_string(),
+            Schema::Scalar => "a string".to_string(),
+            Schema::Bool => "a boolean".to_string(),
+            Schema::Enum(allowed) => format!("one of: {}", allowed.join(", ")),
+            Schema::List(_) => "a list".to_string(),
+            Schema::Dict(_) | Schema::Object { .. } => "a mapping".to_string(),
+            Schema::OneOf(options) => options.iter().map(|o| o.describe()).collect::<Vec<_>>().join(" or "),
+        }
+    }
+}
+
+fn describe_value(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Sequence(_) => "a list",
+        Value::Mapping(_) => "a mapping",
+        Value::Tagged(_) => "a tagged value",
+    }
+}
+
+struct Checker<'a> {
+    file: &'a Path,
+    content: &'a str,
+    partial: bool,
+    report: &'a mut LintReport,
+}
+
+impl Checker<'_> {
+    fn issue(&mut self, path: &[Segment], message: String) {
+        let (line, column) = locate(self.content, path);
+        self.report.push(self.file, line, column, format!("{}: {}", display_path(path), message));
+    }
+
+    fn check(&mut self, value: &Value, schema: &Schema, path: &mut Vec<Segment>) {
+        // `!reset` / `!override` only change how files are merged
+        let value = match value {
+            Value::Tagged(tagged) => &tagged.value,
+            value => value,
+        };
+        // Compose accepts empty values almost everywhere (`environment:`)
+        if value.is_null() && !path.is_empty() {
+            return;
+        }
+
+        match (schema, value) {
+            // Variables are only known once interpolated
+            (Schema::Enum(allowed), Value::String(s)) if !allowed.contains(&s.as_str()) && !s.contains('$') => {
+                self.issue(path, format!("unexpected value '{}', expected {}", s, schema.describe()));
+            }
+            (Schema::List(item), Value::Sequence(items)) => {
+                for (index, entry) in items.iter().enumerate() {
+                    path.push(Segment::Index(index));
+                    self.check(entry, item, path);
+                    path.pop();
+                }
+            }
+            (Schema::Dict(item), Value::Mapping(mapping)) => {
+                for (key, entry) in mapping {
+                    path.push(Segment::Key(key_string(key)));
+                    self.check(entry, item, path);
+                    path.pop();
+                }
+            }
+            (Schema::Object { fields, required, extensions }, Value::Mapping(mapping)) => {
+                if !self.partial {
+                    for field in *required {
+                        if !field.split('|').any(|name| mapping.contains_key(name)) {
+                            let name = field.split('|').next().unwrap_or_default();
+                            self.issue(path, format!("missing required field '{}'", name));
+                        }
+                    }
+                }
+                for (key, entry) in mapping {
+                    let key = key_string(key);
+                    path.push(Segment::Key(key.clone()));
+                    match fields.iter().find(|(name, _)| *name == key) {
+                        Some((_, field_schema)) => self.check(entry, field_schema, path),
+                        None if *extensions && key.starts_with("x-") => {}
+                        None => self.issue(path, "unknown field".to_string()),
+                    }
+                    path.pop();
+                }
+            }
+            (Schema::OneOf(options), value) => match options.iter().find(|option| option.accepts_kind(value)) {
+                Some(option) => self.check(value, option, path),
+                None => self.issue(path, format!("expected {}, found {}", schema.describe(), describe_value(value))),
+            },
+            (schema, value) if schema.accepts_kind(value) => {}
+            (schema, value) => {
+                self.issue(path, format!("expected {}, found {}", schema.describe(), describe_value(value)));
+            }
+        }
+    }
+}
+
+fn key_string(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other).unwrap_or_default().trim_end().to_string(),
+    }
+}
+
+/// Ligne et colonne (à partir de 1) d'une valeur dans un document YAML en style bloc ; à défaut,
+/// celles de son plus proche parent trouvé
+fn locate(content: &str, path: &[Segment]) -> (usize, usize) {
+    // Each line becomes (line, indent, text, is sequence item); `- key: value` also yields `key: value`
+    let mut entries: Vec<(usize, usize, &str, bool)> = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let mut text = line.trim_start();
+        if text.is_empty() || text.starts_with('#') || text == "---" {
+            continue;
+        }
+        let mut indent = line.len() - text.len();
+        while let Some(rest) = text.strip_prefix('-').filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+            entries.push((number, indent, text, true));
+            let content = rest.trim_start();
+            if content.is_empty() {
+                break;
+            }
+            indent += text.len() - content.len();
+            text = content;
+        }
+        if !text.starts_with('-') {
+            entries.push((number, indent, text, false));
+        }
+    }
+
+    let mut location = (1, 1);
+    let mut start = 0;
+    let mut parent: Option<usize> = None;
+    let mut parent_is_item = false;
+    for segment in path {
+        let mut child_indent = None;
+        let mut index = 0;
+        let mut found = None;
+        for (position, &(_, indent, text, is_item)) in entries.iter().enumerate().skip(start) {
+            let in_scope = match parent {
+                None => true,
+                // `key:` may be followed by sequence items at the same indentation
+                Some(parent) => indent > parent || (is_item && indent == parent && !parent_is_item),
+            };
+            if !in_scope {
+                break;
+            }
+            if *child_indent.get_or_insert(indent) != indent {
+                continue;
+            }
+            let matches = match segment {
+                Segment::Index(wanted) if is_item => {
+                    index += 1;
+                    index - 1 == *wanted
+                }
+                Segment::Key(key) if !is_item => entry_key(text) == Some(key.as_str()),
+                _ => false,
+            };
+            if matches {
+                found = Some(position);
+                break;
+            }
+        }
+
+        let Some(position) = found else {
+            break;
+        };
+        let (line, indent, _, is_item) = entries[position];
+        location = (line + 1, indent + 1);
+        start = position + 1;
+        parent = Some(indent);
+        parent_is_item = is_item;
+    }
+
+    location
+}
+
+/// Clé d'une ligne `clé: valeur` (guillemets retirés)
+fn entry_key(text: &str) -> Option<&str> {
+    let (key, rest) = text.split_once(':')?;
+    if !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    Some(key.trim().trim_matches(|c| c == '"' || c == '\''))
+}
+
+const SCALAR_LIST: Schema = Schema::List(&Schema::Scalar);
+const STRING_OR_LIST: Schema = Schema::OneOf(&[Schema::Scalar, SCALAR_LIST]);
+const DICT_OR_LIST: Schema = Schema::OneOf(&[Schema::Dict(&Schema::Any), SCALAR_LIST]);
+const MAPPING: Schema = Schema::Dict(&Schema::Any);
+const STRING_OR_MAPPING: Schema = Schema::OneOf(&[Schema::Scalar, MAPPING]);
+const ENTRIES: Schema = Schema::List(&STRING_OR_MAPPING);
+
+const SECRET: Schema = Schema::Object {
+    fields: &[("secret", Schema::Scalar), ("id", Schema::Scalar), ("env", Schema::Scalar)],
+    required: &["secret|id", "env"],
+    extensions: false,
+};
+const SECRETS_FILE: Schema = Schema::List(&SECRET);
+
+const DEPENDENCY: Schema = Schema::OneOf(&[
+    Schema::Scalar,
+    Schema::Object {
+        fields: &[
+            ("name", Schema::Scalar),
+            ("condition", Schema::Enum(&["started", "healthy"])),
+            ("timeout", Schema::Scalar),
+        ],
+        required: &["name"],
+        extensions: false,
+    },
+]);
+
+const STACK: Schema = Schema::Object {
+    fields: &[
+        ("name", Schema::Scalar),
+        ("path", Schema::Scalar),
+        ("compose_files", SCALAR_LIST),
+        ("enabled", Schema::Bool),
+        ("stack_name", Schema::Scalar),
+        ("secrets", SECRETS_FILE),
+        ("depends_on", Schema::List(&DEPENDENCY)),
+        ("backend", Schema::Enum(&["swarm", "compose"])),
+        ("cluster", Schema::Scalar),
+    ],
+    required: &["name"],
+    extensions: false,
+};
+const STACKS_FILE: Schema = Schema::List(&STACK);
+
+const VOLUME: Schema = Schema::Object {
+    fields: &[
+        ("id", Schema::Scalar),
+        ("type", Schema::Enum(&["volume", "binding"])),
+        ("path", Schema::Scalar),
+    ],
+    required: &["id", "type", "path"],
+    extensions: false,
+};
+const VOLUMES_FILE: Schema = Schema::List(&VOLUME);
+
+const NFS: Schema = Schema::Object {
+    fields: &[("path", Schema::Scalar)],
+    required: &["path"],
+    extensions: false,
+};
+
+const LOCK: Schema = Schema::Object {
+    fields: &[("images", Schema::Dict(&Schema::Scalar))],
+    required: &[],
+    extensions: false,
+};
+
+const MANIFEST: Schema = Schema::Object {
+    fields: &[
+        ("apiVersion", Schema::Enum(&[MANIFEST_API_VERSION])),
+        ("nfs", NFS),
+        ("volumes", VOLUMES_FILE),
+        ("stacks", STACKS_FILE),
+    ],
+    required: &["apiVersion"],
+    extensions: false,
+};
+
+const DEPLOY: Schema = Schema::Object {
+    fields: &[
+        ("mode", Schema::Enum(&["replicated", "global", "replicated-job", "global-job"])),
+        ("replicas", Schema::Scalar),
+        ("labels", DICT_OR_LIST),
+        ("endpoint_mode", Schema::Enum(&["vip", "dnsrr"])),
+        ("placement", MAPPING),
+        ("resources", MAPPING),
+        ("restart_policy", MAPPING),
+        ("update_config", MAPPING),
+        ("rollback_config", MAPPING),
+    ],
+    required: &[],
+    extensions: true,
+};
+
+const HEALTHCHECK: Schema = Schema::Object {
+    fields: &[
+        ("test", STRING_OR_LIST),
+        ("interval", Schema::Scalar),
+        ("timeout", Schema::Scalar),
+        ("retries", Schema::Scalar),
+        ("start_period", Schema::Scalar),
+        ("start_interval", Schema::Scalar),
+        ("disable", Schema::Scalar),
+    ],
+    required: &[],
+    extensions: true,
+};
+
+const SERVICE: Schema = Schema::Object {
+    fields: &[
+        ("annotations", DICT_OR_LIST),
+        ("attach", Schema::Scalar),
+        ("blkio_config", MAPPING),
+        ("build", STRING_OR_MAPPING),
+        ("cap_add", SCALAR_LIST),
+        ("cap_drop", SCALAR_LIST),
+        ("cgroup", Schema::Scalar),
+        ("cgroup_parent", Schema::Scalar),
+        ("command", STRING_OR_LIST),
+        ("configs", ENTRIES),
+        ("container_name", Schema::Scalar),
+        ("cpu_count", Schema::Scalar),
+        ("cpu_percent", Schema::Scalar),
+        ("cpu_period", Schema::Scalar),
+        ("cpu_quota", Schema::Scalar),
+        ("cpu_rt_period", Schema::Scalar),
+        ("cpu_rt_runtime", Schema::Scalar),
+        ("cpu_shares", Schema::Scalar),
+        ("cpus", Schema::Scalar),
+        ("cpuset", Schema::Scalar),
+        ("credential_spec", MAPPING),
+        ("depends_on", DICT_OR_LIST),
+        ("deploy", DEPLOY),
+        ("develop", MAPPING),
+        ("device_cgroup_rules", SCALAR_LIST),
+        ("devices", ENTRIES),
+        ("dns", STRING_OR_LIST),
+        ("dns_opt", SCALAR_LIST),
+        ("dns_search", STRING_OR_LIST),
+        ("domainname", Schema::Scalar),
+        ("entrypoint", STRING_OR_LIST),
+        ("env_file", Schema::OneOf(&[Schema::Scalar, ENTRIES])),
+        ("environment", DICT_OR_LIST),
+        ("expose", SCALAR_LIST),
+        ("extends", STRING_OR_MAPPING),
+        ("external_links", SCALAR_LIST),
+        ("extra_hosts", DICT_OR_LIST),
+        ("gpus", Schema::Any),
+        ("group_add", SCALAR_LIST),
+        ("healthcheck", HEALTHCHECK),
+        ("hostname", Schema::Scalar),
+        ("image", Schema::Scalar),
+        ("init", Schema::Scalar),
+        ("ipc", Schema::Scalar),
+        ("isolation", Schema::Scalar),
+        ("labels", DICT_OR_LIST),
+        ("label_file", STRING_OR_LIST),
+        ("links", SCALAR_LIST),
+        ("logging", MAPPING),
+        ("mac_address", Schema::Scalar),
+        ("mem_limit", Schema::Scalar),
+        ("mem_reservation", Schema::Scalar),
+        ("mem_swappiness", Schema::Scalar),
+        ("memswap_limit", Schema::Scalar),
+        ("network_mode", Schema::Scalar),
+        ("networks", DICT_OR_LIST),
+        ("oom_kill_disable", Schema::Scalar),
+        ("oom_score_adj", Schema::Scalar),
+        ("pid", Schema::Scalar),
+        ("pids_limit", Schema::Scalar),
+        ("platform", Schema::Scalar),
+        ("ports", ENTRIES),
+        ("post_start", ENTRIES),
+        ("pre_stop", ENTRIES),
+        ("privileged", Schema::Scalar),
+        ("profiles", SCALAR_LIST),
+        ("pull_policy", Schema::Scalar),
+        ("read_only", Schema::Scalar),
+        ("restart", Schema::Scalar),
+        ("runtime", Schema::Scalar),
+        ("scale", Schema::Scalar),
+        ("secrets", ENTRIES),
+        ("security_opt", SCALAR_LIST),
+        ("shm_size", Schema::Scalar),
+        ("stdin_open", Schema::Scalar),
+        ("stop_grace_period", Schema::Scalar),
+        ("stop_signal", Schema::Scalar),
+        ("storage_opt", MAPPING),
+        ("sysctls", DICT_OR_LIST),
+        ("tmpfs", STRING_OR_LIST),
+        ("tty", Schema::Scalar),
+        ("ulimits", MAPPING),
+        ("user", Schema::Scalar),
+        ("userns_mode", Schema::Scalar),
+        ("uts", Schema::Scalar),
+        ("volumes", ENTRIES),
+        ("volumes_from", SCALAR_LIST),
+        ("working_dir", Schema::Scalar),
+    ],
+    required: &[],
+    extensions: true,
+};
+
+const COMPOSE: Schema = Schema::Object {
+    fields: &[
+        ("version", Schema::Scalar),
+        ("name", Schema::Scalar),
+        ("include", ENTRIES),
+        ("services", Schema::Dict(&SERVICE)),
+        ("networks", MAPPING),
+        ("volumes", MAPPING),
+        ("secrets", MAPPING),
+        ("configs", MAPPING),
+    ],
+    required: &[],
+    extensions: true,
+};
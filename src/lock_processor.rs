@@ -0,0 +1,50 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Fichier des digests attendus, à la racine du repository
+pub const LOCK_FILE: &str = "dockerops.lock";
+
+/// Contenu de dockerops.lock : référence d'image (telle qu'écrite dans les fichiers compose) -> digest
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LockFile {
+    #[serde(default)]
+    images: BTreeMap<String, String>,
+}
+
+/// Processeur pour le fichier dockerops.lock, qui fixe le digest déployé pour chaque image
+pub struct LockProcessor;
+
+impl LockProcessor {
+    /// Charge les digests attendus du repository ; vide si le fichier n'existe pas
+    pub fn load(repo_path: &Path) -> Result<BTreeMap<String, String>> {
+        let path = repo_path.join(LOCK_FILE);
+        if !path.is_file() {
+            return Ok(BTreeMap::new());
+        }
+        let content = fs::read_to_string(&path)?;
+        Self::parse(&content).map_err(|e| anyhow::anyhow!("Invalid {}: {}", LOCK_FILE, e))
+    }
+
+    /// Lit un fichier dockerops.lock et vérifie que chaque digest est de la forme `sha256:<64 hexadécimaux>`
+    pub fn parse(content: &str) -> Result<BTreeMap<String, String>> {
+        let lock: LockFile = serde_yaml::from_str::<Option<LockFile>>(content)?.unwrap_or_default();
+        for (image, digest) in &lock.images {
+            if image.contains('@') {
+                return Err(anyhow::anyhow!("image '{}' is already pinned to a digest", image));
+            }
+            if !Self::is_valid_digest(digest) {
+                return Err(anyhow::anyhow!("invalid digest '{}' for image '{}', expected sha256:<64 hex characters>", digest, image));
+            }
+        }
+        Ok(lock.images)
+    }
+
+    fn is_valid_digest(digest: &str) -> bool {
+        digest.strip_prefix("sha256:")
+            .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+    }
+}
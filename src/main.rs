@@ -8,6 +8,16 @@ mod compose_processor;
 mod volume_processor;
 mod secret_processor;
 mod stack_processor;
+mod report;
+mod dependency_graph;
+mod overlay_processor;
+mod env_processor;
+mod template_processor;
+mod digest_processor;
+mod manifest_processor;
+mod lint_processor;
+mod discovery_processor;
+mod lock_processor;
 
 use clap::{Parser, Subcommand};
 use anyhow::Result;
@@ -33,12 +43,106 @@ enum Commands {
         #[arg(long)]
         force: bool,
     },
+    /// Reconcile a single repository, or a single stack, optionally at a given revision
+    Sync {
+        /// GitHub repository URL
+        repo: String,
+        /// Only reconcile this stack
+        #[arg(long)]
+        stack: Option<String>,
+        /// Commit SHA, tag or branch to deploy instead of the default branch head
+        #[arg(long)]
+        revision: Option<String>,
+        /// Redeploy even if no changes detected
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the stack dependency graph of a repository as DOT
+    Graph {
+        /// Path to a local checkout of the repository
+        path: String,
+    },
+    /// Validate the manifests and compose files of a repository
+    Lint {
+        /// Path to a local checkout of the repository
+        path: String,
+    },
+    /// Convert stacks.yaml, volumes.yaml, nfs.yaml and secrets.yaml files into dockerops.yaml
+    MigrateManifest {
+        /// Path to a local checkout of the repository
+        path: String,
+        /// Overwrite an existing dockerops.yaml
+        #[arg(long)]
+        force: bool,
+    },
     /// Stop the application
     Stop,
     /// Show version information
     Version,
     /// Debug repository cache
     DebugCache,
+    /// Show deployment history
+    History {
+        /// Only show deployments of this stack
+        #[arg(long)]
+        stack: Option<String>,
+        /// Only show deployments of this repository URL
+        #[arg(long)]
+        repo: Option<String>,
+        /// Only show deployments to this cluster
+        #[arg(long)]
+        cluster: Option<String>,
+        /// Maximum number of deployments to show
+        #[arg(long, default_value_t = 50)]
+        limit: i64,
+    },
+    /// Redeploy a previously recorded deployment of a stack and pin it
+    Rollback {
+        /// Stack name
+        stack: String,
+        /// Deployment ID or commit SHA (prefix) to roll back to; defaults to the previous successful deployment
+        #[arg(long)]
+        to: Option<String>,
+        /// Repository URL, when the stack name exists in several repositories
+        #[arg(long)]
+        repo: Option<String>,
+        /// Cluster, when the stack is deployed to several clusters
+        #[arg(long)]
+        cluster: Option<String>,
+    },
+    /// Unpin a stack pinned by a rollback so that reconcile manages it again
+    Unpin {
+        /// Stack name
+        stack: String,
+        /// Repository URL, when the stack name exists in several repositories
+        #[arg(long)]
+        repo: Option<String>,
+        /// Cluster, when the stack is deployed to several clusters
+        #[arg(long)]
+        cluster: Option<String>,
+    },
+    /// Suspend reconciliation of a stack or a repository
+    Suspend {
+        /// Stack name or repository URL
+        target: String,
+        /// Lift the suspension automatically after this duration (e.g. 30m, 2h, 1d)
+        #[arg(long, value_parser = config::parse_duration)]
+        ttl: Option<std::time::Duration>,
+        /// Free-form reason, shown in reconcile output
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Resume reconciliation of a suspended stack or repository
+    Resume {
+        /// Stack name or repository URL
+        target: String,
+    },
+    /// Update the services whose image tag was moved in its registry since the last deployment
+    CheckImages {
+        /// Only check this stack
+        #[arg(long)]
+        stack: Option<String>,
+    },
     /// Run as daemon: seed repos from DOCKEROPS_REPOS, then reconcile in a loop (DOCKEROPS_SYNC_INTERVAL seconds)
     Run,
 }
@@ -48,13 +152,13 @@ async fn main() -> Result<()> {
     // Check if running as root
     if std::env::var("USER").unwrap_or_default() != "root" {
         eprintln!("❌ Error: DockerOps must be run with root privileges (use sudo)");
-        eprintln!("");
+        eprintln!();
         eprintln!("This is required because DockerOps needs to:");
         eprintln!("  • Execute Docker commands");
         eprintln!("  • Manage Docker Swarm stacks");
         eprintln!("  • Pull and remove Docker images");
         eprintln!("  • Access Docker daemon");
-        eprintln!("");
+        eprintln!();
         eprintln!("Please run: sudo dockerops <command>");
         std::process::exit(1);
     }
@@ -84,12 +188,40 @@ async fn main() -> Result<()> {
         Commands::Watch { url } => {
             let db = database::Database::new(&database_url).await?;
             let commands = commands::Commands::new(db).await?;
-            commands.watch(url).await?;
+            let report = commands.watch(url).await?;
+            if report.has_failures() {
+                std::process::exit(1);
+            }
         }
         Commands::Reconcile { force } => {
             let db = database::Database::new(&database_url).await?;
             let commands = commands::Commands::new(db).await?;
-            commands.reconcile(*force).await?;
+            let report = commands.reconcile(*force).await?;
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Sync { repo, stack, revision, force } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            let report = commands.sync(repo, stack.as_deref(), revision.as_deref(), *force).await?;
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Graph { path } => {
+            // Graph command only reads the local checkout
+            commands::Commands::graph(path)?;
+        }
+        Commands::Lint { path } => {
+            // Lint only reads the local checkout
+            if !commands::Commands::lint(path)? {
+                std::process::exit(1);
+            }
+        }
+        Commands::MigrateManifest { path, force } => {
+            // Migration only rewrites files of the local checkout
+            commands::Commands::migrate_manifest(path, *force)?;
         }
         Commands::Stop => {
             let db = database::Database::new(&database_url).await?;
@@ -105,10 +237,43 @@ async fn main() -> Result<()> {
             let commands = commands::Commands::new(db).await?;
             commands.debug_cache().await?;
         }
+        Commands::History { stack, repo, cluster, limit } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            commands.history(stack.as_deref(), repo.as_deref(), cluster.as_deref(), *limit).await?;
+        }
+        Commands::Rollback { stack, to, repo, cluster } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            commands.rollback(stack, to.as_deref(), repo.as_deref(), cluster.as_deref()).await?;
+        }
+        Commands::Unpin { stack, repo, cluster } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            commands.unpin(stack, repo.as_deref(), cluster.as_deref()).await?;
+        }
+        Commands::Suspend { target, ttl, reason } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            commands.suspend(target, *ttl, reason.as_deref()).await?;
+        }
+        Commands::Resume { target } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            commands.resume(target).await?;
+        }
+        Commands::CheckImages { stack } => {
+            let db = database::Database::new(&database_url).await?;
+            let commands = commands::Commands::new(db).await?;
+            let report = commands.check_images(stack.as_deref()).await?;
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+        }
         Commands::Run => {
             let repo_urls: Vec<String> = std::env::var("DOCKEROPS_REPOS")
                 .unwrap_or_default()
-                .split(|c| c == ',' || c == ';')
+                .split([',', ';'])
                 .map(|s| s.trim().to_string())
                 .filter(|s| !s.is_empty())
                 .collect();
@@ -116,9 +281,14 @@ async fn main() -> Result<()> {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(300);
+            // Image watch is disabled unless an interval is set
+            let image_watch_secs: Option<u64> = std::env::var("DOCKEROPS_IMAGE_WATCH_INTERVAL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0);
             let db = database::Database::new(&database_url).await?;
             let commands = commands::Commands::new(db).await?;
-            commands.run_daemon(&repo_urls, interval_secs).await?;
+            commands.run_daemon(&repo_urls, interval_secs, image_watch_secs).await?;
         }
     }
 
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::DiscoveryConfig;
+use crate::discovery_processor::DiscoveryProcessor;
+use crate::models::{Manifest, SecretDefinition, MANIFEST_API_VERSION};
+use crate::overlay_processor::OverlayProcessor;
+use crate::secret_processor::SecretProcessor;
+use crate::volume_processor::VolumeProcessor;
+
+/// Nom du manifeste unifié d'un repository
+pub const MANIFEST_FILE: &str = "dockerops.yaml";
+
+/// Fichiers de configuration historiques remplacés par dockerops.yaml
+const LEGACY_FILES: [&str; 3] = ["stacks.yaml", "volumes.yaml", "nfs.yaml"];
+
+/// Processeur pour la configuration d'un repository : dockerops.yaml, ou à défaut
+/// les fichiers historiques stacks.yaml, volumes.yaml et nfs.yaml
+pub struct ManifestProcessor;
+
+/// Résultat de `migrate-manifest`
+pub struct Migration {
+    pub manifest_path: PathBuf,
+    /// Fichiers désormais ignorés, qui peuvent être supprimés
+    pub replaced_files: Vec<PathBuf>,
+    /// Overlays d'environnement des fichiers historiques, à convertir manuellement
+    pub overlay_files: Vec<PathBuf>,
+}
+
+impl ManifestProcessor {
+    /// Charge la configuration du repository avec ses overlays d'environnement.
+    /// dockerops.yaml (ou l'un de ses overlays) est prioritaire sur les fichiers historiques ;
+    /// sans stacks.yaml, les stacks sont découverts si `discovery` est activée.
+    pub fn load(repo_path: &Path, environment: Option<&str>, discovery: Option<&DiscoveryConfig>) -> Result<Manifest> {
+        let Some(manifest) = OverlayProcessor::load::<Manifest>(repo_path, MANIFEST_FILE, None, environment)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", MANIFEST_FILE, e))?
+        else {
+            return Self::load_legacy(repo_path, environment, discovery);
+        };
+
+        if manifest.api_version != MANIFEST_API_VERSION {
+            return Err(anyhow::anyhow!(
+                "Unsupported apiVersion '{}' in {} (expected '{}')",
+                manifest.api_version, MANIFEST_FILE, MANIFEST_API_VERSION
+            ));
+        }
+        if !manifest.volumes.is_empty() && manifest.nfs.is_none() {
+            return Err(anyhow::anyhow!("{} declares volumes but no nfs section", MANIFEST_FILE));
+        }
+
+        let ignored: Vec<&str> = LEGACY_FILES.into_iter().filter(|file| repo_path.join(file).is_file()).collect();
+        if !ignored.is_empty() {
+            eprintln!("Warning: {} found, ignoring {}", MANIFEST_FILE, ignored.join(", "));
+        }
+
+        Ok(manifest)
+    }
+
+    /// Charge stacks.yaml (ou les stacks découverts), volumes.yaml et nfs.yaml
+    /// (nfs.yaml n'est requis qu'avec des volumes)
+    fn load_legacy(repo_path: &Path, environment: Option<&str>, discovery: Option<&DiscoveryConfig>) -> Result<Manifest> {
+        let repo = repo_path.to_string_lossy();
+        let stacks = match (OverlayProcessor::load(repo_path, "stacks.yaml", Some("name"), environment)?, discovery) {
+            (Some(stacks), _) => stacks,
+            (None, Some(discovery)) => DiscoveryProcessor::discover(repo_path, discovery)?,
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Neither {} nor stacks.yaml found in repository (set DOCKEROPS_DISCOVERY=true to discover stacks)",
+                    MANIFEST_FILE
+                ));
+            }
+        };
+        let volumes = VolumeProcessor::load_volumes_config(&repo, environment)?.unwrap_or_default();
+        let nfs = if volumes.is_empty() {
+            None
+        } else {
+            Some(SecretProcessor::load_nfs_config(&repo, environment)?)
+        };
+
+        Ok(Manifest {
+            api_version: MANIFEST_API_VERSION.to_string(),
+            nfs,
+            volumes,
+            stacks,
+        })
+    }
+
+    /// Convertit les fichiers historiques (sans overlays) et les secrets.yaml des stacks en dockerops.yaml
+    pub fn migrate(repo_path: &Path, force: bool) -> Result<Migration> {
+        let manifest_path = repo_path.join(MANIFEST_FILE);
+        if manifest_path.exists() && !force {
+            return Err(anyhow::anyhow!("{} already exists (use --force to overwrite)", manifest_path.display()));
+        }
+
+        let mut manifest = Self::load_legacy(repo_path, None, None)?;
+        let mut replaced_files: Vec<PathBuf> = LEGACY_FILES.iter()
+            .map(|file| repo_path.join(file))
+            .filter(|path| path.is_file())
+            .collect();
+
+        for stack_def in &mut manifest.stacks {
+            if stack_def.secrets.is_some() {
+                continue;
+            }
+            let secrets_path = repo_path.join(stack_def.directory()).join("secrets.yaml");
+            if !secrets_path.is_file() {
+                continue;
+            }
+            let content = fs::read_to_string(&secrets_path)?;
+            let secrets: Vec<SecretDefinition> = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", secrets_path.display(), e))?;
+            stack_def.secrets = Some(secrets);
+            replaced_files.push(secrets_path);
+        }
+
+        let overlay_files = Self::legacy_overlays(repo_path);
+        fs::write(&manifest_path, serde_yaml::to_string(&manifest)?)?;
+
+        Ok(Migration { manifest_path, replaced_files, overlay_files })
+    }
+
+    /// Overlays d'environnement des fichiers historiques (`stacks.<env>.yaml`, `overlays/<env>/stacks.yaml`...)
+    fn legacy_overlays(repo_path: &Path) -> Vec<PathBuf> {
+        let mut overlays = Vec::new();
+        let Ok(entries) = fs::read_dir(repo_path) else {
+            return overlays;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if LEGACY_FILES.iter().any(|file| {
+                let stem = file.trim_end_matches(".yaml");
+                name.starts_with(&format!("{}.", stem)) && name.ends_with(".yaml") && name != *file
+            }) {
+                overlays.push(entry.path());
+            }
+        }
+        if let Ok(environments) = fs::read_dir(repo_path.join("overlays")) {
+            for environment in environments.flatten() {
+                overlays.extend(LEGACY_FILES.iter().map(|file| environment.path().join(file)).filter(|path| path.is_file()));
+            }
+        }
+        overlays.sort();
+        overlays
+    }
+}
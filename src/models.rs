@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use crate::config::{DeployBackend, DEFAULT_CLUSTER};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Image {
@@ -18,6 +19,28 @@ pub struct Stack {
     pub compose_path: String,
     pub hash: String,
     pub status: String, // "deployed", "stopped", "error"
+    pub pinned: bool, // Pinned by a rollback: reconcile leaves the stack untouched until unpinned
+    pub depends_on: String, // Comma-separated names of the stacks it depends on
+    pub service_digests: String, // JSON object: service -> SHA-256 of the files it references
+    pub backend: String, // "swarm", "compose"
+    pub cluster: String, // Name of the target cluster
+}
+
+/// Enregistrement d'une tentative de déploiement d'un stack
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Deployment {
+    pub id: i64,
+    pub stack_name: String,
+    pub repository_url: String,
+    pub commit_sha: Option<String>,
+    pub rendered_compose: String,
+    pub image_digests: String, // JSON object: image -> digest
+    pub resolved_images: String, // JSON array: tag references pinned to a digest at render time
+    pub started_at: String, // ISO timestamp
+    pub finished_at: Option<String>, // ISO timestamp
+    pub status: String, // "running", "succeeded", "failed"
+    pub error: Option<String>,
+    pub cluster: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -27,9 +50,111 @@ pub struct RepositoryCache {
     pub last_watch: String, // ISO timestamp
 }
 
+/// Suspension de la réconciliation d'un stack ou de tout un repository
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Suspension {
+    pub id: i64,
+    pub target: String, // Stack name or repository URL
+    pub kind: String, // "stack", "repository"
+    pub reason: Option<String>,
+    pub created_at: String, // ISO timestamp
+    pub expires_at: Option<String>, // ISO timestamp, None = until resumed
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StackDefinition {
     pub name: String,
+    /// Dossier du stack relatif à la racine du repository (par défaut : `name`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Fichiers compose relatifs au dossier du stack, fusionnés dans l'ordre (par défaut : détection automatique)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compose_files: Vec<String>,
+    /// Un stack désactivé n'est ni déployé ni mis à jour
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    /// Nom du stack dans le Swarm (par défaut : `name`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_name: Option<String>,
+    /// Secrets du stack (dockerops.yaml) ; à défaut, `secrets.yaml` dans le dossier du stack
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<Vec<SecretDefinition>>,
+    /// Stacks à déployer (et éventuellement attendre) avant celui-ci
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<StackDependency>,
+    /// Mode de déploiement du stack (par défaut : `DOCKEROPS_BACKEND`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<DeployBackend>,
+    /// Cluster cible du stack (par défaut : les clusters auxquels le repository est affecté)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl StackDefinition {
+    /// Dossier du stack relatif à la racine du repository
+    pub fn directory(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Nom sous lequel le stack est déployé dans le Swarm et enregistré en base
+    pub fn swarm_name(&self) -> &str {
+        self.stack_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Dépendance d'un stack envers un autre : `- db` ou `- { name: db, condition: healthy, timeout: 300 }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StackDependency {
+    Name(String),
+    Detailed {
+        name: String,
+        #[serde(default)]
+        condition: DependencyCondition,
+        /// Délai maximal d'attente de santé, en secondes
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout: Option<u64>,
+    },
+}
+
+/// Condition à remplir par une dépendance avant le déploiement du stack dépendant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyCondition {
+    /// La dépendance a été déployée
+    #[default]
+    Started,
+    /// Toutes les tâches de la dépendance sont en cours d'exécution (healthchecks passés)
+    Healthy,
+}
+
+impl StackDependency {
+    pub fn name(&self) -> &str {
+        match self {
+            StackDependency::Name(name) => name,
+            StackDependency::Detailed { name, .. } => name,
+        }
+    }
+
+    pub fn wait_healthy(&self) -> bool {
+        matches!(self, StackDependency::Detailed { condition: DependencyCondition::Healthy, .. })
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        let secs = match self {
+            StackDependency::Detailed { timeout: Some(timeout), .. } => *timeout,
+            _ => 300,
+        };
+        std::time::Duration::from_secs(secs)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -52,7 +177,7 @@ pub struct NfsConfig {
 }
 
 /// Declaration of a Docker Swarm secret (name) and the env var to expose its file content as.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecretDefinition {
     /// Docker Swarm secret name (created with docker secret create). Accepts "id" in YAML for backward compat.
     #[serde(alias = "id")]
@@ -61,17 +186,23 @@ pub struct SecretDefinition {
     pub env: String,
 }
 
-impl Image {
-    pub fn new(name: String, reference_count: i32) -> Self {
-        Self {
-            id: 0, // Will be set by database
-            name,
-            reference_count,
-        }
-    }
-}
-
+/// Version du format de dockerops.yaml
+pub const MANIFEST_API_VERSION: &str = "dockerops/v1";
 
+/// Manifeste unifié d'un repository (dockerops.yaml), qui regroupe stacks.yaml, volumes.yaml,
+/// nfs.yaml et les secrets.yaml de chaque stack
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(rename = "apiVersion")]
+    pub api_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nfs: Option<NfsConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<VolumeDefinition>,
+    #[serde(default)]
+    pub stacks: Vec<StackDefinition>,
+}
 
 impl Stack {
     pub fn new(name: String, repository_url: String, compose_path: String, hash: String) -> Self {
@@ -82,6 +213,50 @@ impl Stack {
             compose_path,
             hash,
             status: "stopped".to_string(),
+            pinned: false,
+            depends_on: String::new(),
+            service_digests: "{}".to_string(),
+            backend: DeployBackend::default().as_str().to_string(),
+            cluster: DEFAULT_CLUSTER.to_string(),
+        }
+    }
+
+    /// Mode avec lequel le stack a été déployé
+    pub fn deploy_backend(&self) -> DeployBackend {
+        DeployBackend::from_str(&self.backend).unwrap_or_default()
+    }
+
+    /// Retourne les empreintes des entrées de chaque service (service -> digest)
+    pub fn service_digests_map(&self) -> std::collections::BTreeMap<String, String> {
+        serde_json::from_str(&self.service_digests).unwrap_or_default()
+    }
+}
+
+impl Deployment {
+    pub fn new(stack_name: String, repository_url: String, commit_sha: Option<String>, rendered_compose: String) -> Self {
+        Self {
+            id: 0, // Will be set by database
+            stack_name,
+            repository_url,
+            commit_sha,
+            rendered_compose,
+            image_digests: "{}".to_string(),
+            resolved_images: "[]".to_string(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            status: "running".to_string(),
+            error: None,
+            cluster: DEFAULT_CLUSTER.to_string(),
         }
     }
-} 
\ No newline at end of file
+
+    /// Retourne les digests d'images enregistrés (image -> digest)
+    pub fn image_digests_map(&self) -> std::collections::BTreeMap<String, String> {
+        serde_json::from_str(&self.image_digests).unwrap_or_default()
+    }
+
+    /// Retourne les références par tag que DockerOps a épinglées à un digest au rendu, hors dockerops.lock
+    pub fn resolved_images_set(&self) -> std::collections::BTreeSet<String> {
+        serde_json::from_str(&self.resolved_images).unwrap_or_default()
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_yaml::Value;
+use crate::template_processor::TemplateProcessor;
+
+/// Processeur pour les overlays d'environnement (`stacks.prod.yaml`, `overlays/prod/`, `docker-compose.prod.yml`)
+pub struct OverlayProcessor;
+
+impl OverlayProcessor {
+    /// Fichiers overlay existants d'un fichier de configuration, dans l'ordre d'application :
+    /// `<nom>.<env>.<ext>` puis `overlays/<env>/<nom>.<ext>`. Un fichier caché sans extension est suffixé,
+    /// comme le fait Compose : `.env` -> `.env.<env>`
+    pub fn overlay_files(dir: &Path, file_name: &str, environment: Option<&str>) -> Vec<PathBuf> {
+        let Some(environment) = environment else {
+            return Vec::new();
+        };
+
+        // `stacks.yaml` -> `stacks.prod.yaml`, but `.env` -> `.env.prod`
+        let suffixed = match file_name.rsplit_once('.') {
+            Some((stem, extension)) if !stem.is_empty() => format!("{}.{}.{}", stem, environment, extension),
+            _ => format!("{}.{}", file_name, environment),
+        };
+
+        [dir.join(suffixed), dir.join("overlays").join(environment).join(file_name)]
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Charge un fichier de configuration YAML du repository en appliquant ses overlays d'environnement.
+    /// Les listes sont fusionnées entrée par entrée selon `key` (champs de l'overlay prioritaires,
+    /// nouvelles entrées ajoutées à la fin), ou concaténées sans `key` ; les mappings sont fusionnés champ par champ.
+    /// Retourne `None` si ni le fichier ni aucun overlay n'existe.
+    pub fn load<T: serde::de::DeserializeOwned>(
+        dir: &Path,
+        file_name: &str,
+        key: Option<&str>,
+        environment: Option<&str>,
+    ) -> Result<Option<T>> {
+        let base_path = dir.join(file_name);
+        let mut files: Vec<PathBuf> = if base_path.is_file() { vec![base_path] } else { Vec::new() };
+        files.extend(Self::overlay_files(dir, file_name, environment));
+
+        let mut merged: Option<Value> = None;
+        for path in &files {
+            let content = fs::read_to_string(path)?;
+            let value: Value = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))?;
+            merged = Some(match merged {
+                Some(base) => Self::merge(base, value, key),
+                None => value,
+            });
+        }
+
+        match merged {
+            Some(value) => Ok(Some(serde_yaml::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fichiers compose overlay d'un stack pour l'environnement (ou leurs templates `.tmpl`),
+    /// à fusionner après les fichiers de base
+    pub fn compose_overlays(stack_dir: &Path, environment: Option<&str>) -> Vec<PathBuf> {
+        let Some(environment) = environment else {
+            return Vec::new();
+        };
+
+        let overlay_dir = stack_dir.join("overlays").join(environment);
+        let mut candidates: Vec<PathBuf> = ["docker-compose", "compose"].iter()
+            .flat_map(|stem| ["yml", "yaml"].map(|ext| stack_dir.join(format!("{}.{}.{}", stem, environment, ext))))
+            .collect();
+        candidates.extend(
+            ["docker-compose.yml", "docker-compose.yaml", "compose.yml", "compose.yaml"]
+                .iter()
+                .map(|name| overlay_dir.join(name)),
+        );
+
+        candidates.into_iter().filter_map(|path| TemplateProcessor::existing_file(&path)).collect()
+    }
+
+    /// Clé identifiant les entrées d'une liste de dockerops.yaml
+    fn list_key(field: &str) -> Option<&'static str> {
+        match field {
+            "stacks" => Some("name"),
+            "volumes" => Some("id"),
+            "secrets" => Some("secret"),
+            _ => None,
+        }
+    }
+
+    fn merge(base: Value, overlay: Value, key: Option<&str>) -> Value {
+        match (base, overlay) {
+            (Value::Sequence(mut entries), Value::Sequence(overlay_entries)) => {
+                for overlay_entry in overlay_entries {
+                    let existing = key
+                        .and_then(|key| overlay_entry.get(key).map(|id| (key, id)))
+                        .and_then(|(key, id)| entries.iter().position(|entry| entry.get(key) == Some(id)));
+                    match (existing, overlay_entry) {
+                        (Some(index), Value::Mapping(fields)) => {
+                            if let Some(entry) = entries[index].as_mapping_mut() {
+                                entry.extend(fields);
+                            }
+                        }
+                        (_, overlay_entry) => entries.push(overlay_entry),
+                    }
+                }
+                Value::Sequence(entries)
+            }
+            (Value::Mapping(mut fields), Value::Mapping(overlay_fields)) => {
+                // Sections of dockerops.yaml are merged like the legacy files they replace
+                for (field, value) in overlay_fields {
+                    let merged = match fields.get_mut(&field) {
+                        Some(existing) => {
+                            let list_key = field.as_str().and_then(Self::list_key);
+                            Self::merge(std::mem::take(existing), value, list_key)
+                        }
+                        None => value,
+                    };
+                    fields.insert(field, merged);
+                }
+                Value::Mapping(fields)
+            }
+            (_, overlay) => overlay,
+        }
+    }
+}
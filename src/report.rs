@@ -0,0 +1,135 @@
+use std::fmt;
+use crate::config::DEFAULT_CLUSTER;
+
+/// Résultat du traitement d'un élément (repository ou stack) pendant une réconciliation
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// Le stack a été (re)déployé
+    Deployed,
+    /// Aucun changement détecté
+    Unchanged,
+    /// Volontairement ignoré (épinglé, suspendu, dossier absent...)
+    Skipped(String),
+    /// Échec du traitement
+    Failed(String),
+}
+
+impl Outcome {
+    /// Raison d'un saut ou d'un échec (première ligne), vide sinon
+    pub fn detail(&self) -> &str {
+        match self {
+            Outcome::Skipped(reason) | Outcome::Failed(reason) => reason.lines().next().unwrap_or_default(),
+            _ => "",
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Deployed => write!(f, "deployed"),
+            Outcome::Unchanged => write!(f, "unchanged"),
+            Outcome::Skipped(_) => write!(f, "skipped"),
+            Outcome::Failed(_) => write!(f, "FAILED"),
+        }
+    }
+}
+
+/// Ligne du rapport : un stack d'un repository, ou le repository lui-même si `stack` est vide
+#[derive(Debug, Clone)]
+pub struct ReportEntry {
+    pub repository: String,
+    pub stack: Option<String>,
+    /// Cluster sur lequel le stack a été traité
+    pub cluster: Option<String>,
+    pub outcome: Outcome,
+}
+
+/// Rapport d'une réconciliation, affiché en fin d'exécution
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    entries: Vec<ReportEntry>,
+}
+
+impl ReconcileReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, repository: &str, stack: Option<&str>, outcome: Outcome) {
+        self.entries.push(ReportEntry {
+            repository: repository.to_string(),
+            stack: stack.map(|s| s.to_string()),
+            cluster: None,
+            outcome,
+        });
+    }
+
+    pub fn extend(&mut self, other: ReconcileReport) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Rattache les entrées des stacks au cluster sur lequel elles ont été traitées
+    pub fn with_cluster(mut self, cluster: &str) -> Self {
+        for entry in self.entries.iter_mut().filter(|e| e.stack.is_some()) {
+            entry.cluster = Some(cluster.to_string());
+        }
+        self
+    }
+
+    /// Indique si un repository n'a pas pu être traité du tout (clone ou configuration en échec)
+    pub fn repository_failed(&self, repository: &str) -> bool {
+        self.entries.iter().any(|e| {
+            e.repository == repository && e.stack.is_none() && matches!(e.outcome, Outcome::Failed(_))
+        })
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.entries.iter().any(|e| matches!(e.outcome, Outcome::Failed(_)))
+    }
+
+    /// Noms des stacks en échec
+    pub fn failed_stacks(&self) -> Vec<String> {
+        self.entries.iter()
+            .filter(|e| matches!(e.outcome, Outcome::Failed(_)))
+            .filter_map(|e| e.stack.clone())
+            .collect()
+    }
+
+    pub fn failure_count(&self) -> usize {
+        self.entries.iter().filter(|e| matches!(e.outcome, Outcome::Failed(_))).count()
+    }
+
+    /// Affiche le tableau récapitulatif
+    pub fn print_summary(&self) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        println!("\nSummary:");
+        println!("  {:<10} {:<24} {:<50} DETAIL", "RESULT", "STACK", "REPOSITORY");
+        for entry in &self.entries {
+            let detail = entry.outcome.detail();
+            // Stacks of other clusters than the default one are shown as stack@cluster
+            let stack = match (&entry.stack, &entry.cluster) {
+                (Some(stack), Some(cluster)) if cluster != DEFAULT_CLUSTER => format!("{}@{}", stack, cluster),
+                (Some(stack), _) => stack.clone(),
+                (None, _) => "-".to_string(),
+            };
+            println!(
+                "  {:<10} {:<24} {:<50} {}",
+                entry.outcome.to_string(),
+                stack,
+                entry.repository,
+                detail,
+            );
+        }
+
+        let failures = self.failure_count();
+        if failures > 0 {
+            println!("\n❌ {} of {} items failed", failures, self.entries.len());
+        } else {
+            println!("\n✅ {} items processed without errors", self.entries.len());
+        }
+    }
+}
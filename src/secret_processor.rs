@@ -1,4 +1,5 @@
 use anyhow::Result;
+use crate::overlay_processor::OverlayProcessor;
 use std::path::Path;
 use std::fs;
 use crate::models::{SecretDefinition, NfsConfig};
@@ -8,28 +9,29 @@ pub struct SecretProcessor;
 
 impl SecretProcessor {
     /// Charge la configuration NFS depuis nfs.yaml
-    pub fn load_nfs_config(repo_path: &str) -> Result<NfsConfig> {
-        let nfs_file_path = Path::new(repo_path).join("nfs.yaml");
-        if !nfs_file_path.exists() {
-            return Err(anyhow::anyhow!("nfs.yaml not found at: {}", nfs_file_path.display()));
-        }
-
-        let nfs_content = fs::read_to_string(&nfs_file_path)?;
-        let config = serde_yaml::from_str::<NfsConfig>(&nfs_content)?;
-
-        Ok(config)
+    pub fn load_nfs_config(repo_path: &str, environment: Option<&str>) -> Result<NfsConfig> {
+        OverlayProcessor::load(Path::new(repo_path), "nfs.yaml", None, environment)?
+            .ok_or_else(|| anyhow::anyhow!("nfs.yaml not found at: {}", Path::new(repo_path).join("nfs.yaml").display()))
     }
 
-    /// Lit secrets.yaml (déclaration uniquement), génère entrypoint-secrets.sh, retourne les définitions.
+    /// Utilise les secrets déclarés dans dockerops.yaml, sinon lit secrets.yaml (déclaration uniquement),
+    /// génère entrypoint-secrets.sh, retourne les définitions.
     /// Ne lit aucune valeur de secret (plus de NFS). Les secrets sont créés avec docker secret create.
-    pub fn process_secrets(stack_dir: &Path) -> Result<Option<Vec<SecretDefinition>>> {
-        let secrets_file_path = stack_dir.join("secrets.yaml");
-        if !secrets_file_path.exists() {
-            return Ok(None);
-        }
-
-        let secrets_content = fs::read_to_string(&secrets_file_path)?;
-        let definitions: Vec<SecretDefinition> = serde_yaml::from_str(&secrets_content)?;
+    pub fn process_secrets(
+        stack_dir: &Path,
+        manifest_secrets: Option<&[SecretDefinition]>,
+    ) -> Result<Option<Vec<SecretDefinition>>> {
+        let definitions: Vec<SecretDefinition> = match manifest_secrets {
+            Some(secrets) => secrets.to_vec(),
+            None => {
+                let secrets_file_path = stack_dir.join("secrets.yaml");
+                if !secrets_file_path.exists() {
+                    return Ok(None);
+                }
+                let secrets_content = fs::read_to_string(&secrets_file_path)?;
+                serde_yaml::from_str(&secrets_content)?
+            }
+        };
         if definitions.is_empty() {
             return Ok(None);
         }
@@ -1,243 +1,1020 @@
 use anyhow::Result;
-use std::path::Path;
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
 use std::fs;
 use std::sync::Arc;
-use md5;
+use crate::config::{Config, DeployBackend};
 use crate::database::Database;
-use crate::models::{Stack, StackDefinition};
+use crate::dependency_graph::DependencyGraph;
+use crate::models::{Deployment, NfsConfig, Stack, StackDefinition, VolumeDefinition};
 use crate::compose_processor::ComposeProcessor;
 use crate::volume_processor::VolumeProcessor;
 use crate::secret_processor::SecretProcessor;
-use crate::docker::{ImageService, StackService};
+use crate::overlay_processor::OverlayProcessor;
+use crate::manifest_processor::ManifestProcessor;
+use crate::lint_processor::LintProcessor;
+use crate::lock_processor::LockProcessor;
+use crate::env_processor::EnvProcessor;
+use crate::template_processor::TemplateProcessor;
+use crate::digest_processor::DigestProcessor;
+use crate::docker::ClusterServices;
+use crate::github::RepositoryService;
+use crate::report::{Outcome, ReconcileReport};
+
+/// Noms standard d'un fichier compose, par ordre de préférence
+pub const COMPOSE_FILE_NAMES: [&str; 4] = ["docker-compose.yml", "docker-compose.yaml", "compose.yml", "compose.yaml"];
+
+/// Contexte partagé par tous les stacks d'un même repository
+struct RepoContext<'a> {
+    repo_path: &'a str,
+    repository_url: &'a str,
+    commit_sha: Option<String>,
+    force: bool,
+    /// Incrémenter les compteurs de références des images des stacks traités
+    count_images: bool,
+    volumes_definitions: Option<&'a [VolumeDefinition]>,
+    nfs_config: Option<&'a NfsConfig>,
+    /// Nom dans stacks.yaml -> nom du stack dans le Swarm
+    swarm_names: HashMap<String, String>,
+    /// Nom du stack dans le Swarm -> mode de déploiement
+    backends: HashMap<String, DeployBackend>,
+    /// Noms dans le Swarm des stacks déployés sur le cluster traité
+    targeted: HashSet<String>,
+    /// Valeurs de templates du repository (values.yaml et overlays d'environnement)
+    values: serde_yaml::Value,
+    /// Digests attendus des images (dockerops.lock)
+    locked_digests: BTreeMap<String, String>,
+}
+
+impl RepoContext<'_> {
+    /// Nom Swarm d'un stack désigné par son nom dans stacks.yaml
+    fn swarm_name<'n>(&'n self, name: &'n str) -> &'n str {
+        self.swarm_names.get(name).map(String::as_str).unwrap_or(name)
+    }
 
-/// Processeur pour gérer les stacks Docker
+    /// Mode de déploiement d'un stack désigné par son nom dans le Swarm
+    fn backend(&self, swarm_name: &str) -> DeployBackend {
+        self.backends.get(swarm_name).copied().unwrap_or_default()
+    }
+}
+
+/// Processeur pour gérer les stacks Docker d'un cluster
 pub struct StackProcessor {
     db: Arc<Database>,
-    image_service: Arc<ImageService>,
-    stack_service: Arc<StackService>,
+    repo_service: Arc<RepositoryService>,
+    cluster: ClusterServices,
+    config: Config,
 }
 
 impl StackProcessor {
-    /// Crée un nouveau processeur de stacks
-    pub fn new(db: Arc<Database>, image_service: Arc<ImageService>, stack_service: Arc<StackService>) -> Self {
+    /// Crée un nouveau processeur de stacks pour un cluster
+    pub fn new(db: Arc<Database>, repo_service: Arc<RepositoryService>, cluster: ClusterServices, config: Config) -> Self {
         Self {
             db,
-            image_service,
-            stack_service,
+            repo_service,
+            cluster,
+            config,
         }
     }
 
-    /// Traite et déploie les stacks depuis un répertoire
+    /// Indique si un stack est déployé sur un cluster : celui qu'il désigne, sinon ceux du repository
+    pub fn targets_cluster(config: &Config, repository_url: &str, stack_def: &StackDefinition, cluster: &str) -> bool {
+        match &stack_def.cluster {
+            Some(stack_cluster) => stack_cluster == cluster,
+            None => config.repository_clusters(repository_url).contains(&cluster),
+        }
+    }
+
+    /// Traite et déploie sur le cluster les stacks d'un répertoire qui le ciblent (seulement `stack_filter` s'il est donné).
+    /// Chaque stack est traité indépendamment : son échec est consigné dans le rapport sans interrompre les autres.
+    /// Une erreur n'est retournée que si la configuration du repository elle-même est illisible.
+    /// Avec `count_images`, les compteurs de références d'images sont incrémentés ; leur réinitialisation
+    /// incombe à l'appelant.
     pub async fn process_and_deploy_stacks(
         &self,
         repo_path: &str,
         repository_url: &str,
-        is_reconcile: bool,
         force: bool,
-    ) -> Result<()> {
-        // Reset image reference counts at the beginning
-        self.db.reset_image_reference_counts().await?;
+        stack_filter: Option<&str>,
+        count_images: bool,
+    ) -> Result<ReconcileReport> {
+        let environment = self.config.environment.as_deref();
+
+        // Nothing is deployed unless every manifest and compose file is valid
+        let discovery = self.config.discovery.as_ref();
+        let lint = LintProcessor::lint(Path::new(repo_path), environment, discovery);
+        if !lint.issues.is_empty() {
+            let issues: Vec<String> = lint.issues.iter().map(|issue| issue.to_string()).collect();
+            return Err(anyhow::anyhow!("Invalid repository configuration, nothing deployed:\n  {}", issues.join("\n  ")));
+        }
+
+        let manifest = ManifestProcessor::load(Path::new(repo_path), environment, discovery)?;
+        let stacks_definitions = manifest.stacks;
 
-        // Look for stacks.yaml file
-        let stacks_file_path = Path::new(repo_path).join("stacks.yaml");
-        if !stacks_file_path.exists() {
-            return Err(anyhow::anyhow!("stacks.yaml not found in repository"));
+        // Two entries deployed under the same Swarm name would overwrite each other
+        let mut swarm_names: HashMap<String, String> = HashMap::new();
+        let mut backends: HashMap<String, DeployBackend> = HashMap::new();
+        let mut targeted: HashSet<String> = HashSet::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+        for stack_def in &stacks_definitions {
+            if !seen.insert(stack_def.swarm_name()) {
+                return Err(anyhow::anyhow!("Stack name '{}' is used by several entries of stacks.yaml", stack_def.swarm_name()));
+            }
+            if let Some(cluster) = &stack_def.cluster {
+                if self.config.cluster(cluster).is_none() {
+                    return Err(anyhow::anyhow!("Stack '{}' targets unknown cluster '{}'", stack_def.name, cluster));
+                }
+            }
+            swarm_names.insert(stack_def.name.clone(), stack_def.swarm_name().to_string());
+            backends.insert(
+                stack_def.swarm_name().to_string(),
+                stack_def.backend.unwrap_or(self.config.cluster_backend(&self.cluster.name)),
+            );
+            if Self::targets_cluster(&self.config, repository_url, stack_def, &self.cluster.name) {
+                targeted.insert(stack_def.swarm_name().to_string());
+            }
         }
 
-        // Read and parse stacks.yaml
-        let stacks_content = fs::read_to_string(&stacks_file_path)?;
-        let stacks_definitions: Vec<StackDefinition> = serde_yaml::from_str(&stacks_content)?;
+        // A stack selected on the command line is only deployed by the clusters it targets
+        if let Some(stack_name) = stack_filter {
+            let selected = stacks_definitions.iter().find(|s| s.name == stack_name || s.swarm_name() == stack_name);
+            if selected.is_some_and(|s| !targeted.contains(s.swarm_name())) {
+                return Ok(ReconcileReport::new());
+            }
+        }
 
-        // Process volumes configuration
-        let mut volumes_definitions = VolumeProcessor::load_volumes_config(repo_path)?;
-        
-        // Load NFS config if volumes exist
-        let nfs_config = if volumes_definitions.is_some() {
-            Some(SecretProcessor::load_nfs_config(repo_path)?)
-        } else {
-            None
+        // Deployment waves follow depends_on; a single selected stack is deployed on its own
+        let graph = DependencyGraph::from_definitions(&stacks_definitions)?;
+        let mut levels = match stack_filter {
+            Some(stack_name) => {
+                let stack_def = stacks_definitions.iter()
+                    .find(|s| s.name == stack_name || s.swarm_name() == stack_name)
+                    .ok_or_else(|| anyhow::anyhow!("Stack '{}' not found in stacks.yaml", stack_name))?;
+                vec![vec![stack_def.name.clone()]]
+            }
+            None => graph.levels()?,
         };
+        for level in &mut levels {
+            level.retain(|name| targeted.contains(swarm_names.get(name).map(String::as_str).unwrap_or(name)));
+        }
+
+        let values = TemplateProcessor::load_values(Path::new(repo_path), environment)?;
+
+        // A missing required variable in any stack stops the repository before anything is deployed
+        let selected: Vec<&StackDefinition> = stacks_definitions.iter()
+            .filter(|s| levels.iter().flatten().any(|name| *name == s.name))
+            .collect();
+        self.check_required_variables(&selected, repo_path, &values)?;
+
+        // Volumes and NFS configuration (nfs is only loaded when volumes exist)
+        let mut volumes_definitions = (!manifest.volumes.is_empty()).then_some(manifest.volumes);
+        let nfs_config = manifest.nfs;
 
         // Process volumes if they exist
         if let Some(ref mut volumes_defs) = volumes_definitions {
             if let Some(ref nfs_config) = nfs_config {
-                VolumeProcessor::process_volumes(volumes_defs, Some(nfs_config), repo_path).await?;
+                let template_context = TemplateProcessor::context(&values, environment, None);
+                VolumeProcessor::process_volumes(volumes_defs, Some(nfs_config), repo_path, &template_context).await?;
             }
         }
 
-        // Process each stack
-        for stack_def in &stacks_definitions {
-            self.process_stack(
-                stack_def,
-                repo_path,
-                repository_url,
-                is_reconcile,
-                force,
-                volumes_definitions.as_deref(),
-                nfs_config.as_ref(),
-            ).await?;
+        let ctx = RepoContext {
+            repo_path,
+            repository_url,
+            commit_sha: RepositoryService::head_commit(repo_path),
+            force,
+            count_images,
+            volumes_definitions: volumes_definitions.as_deref(),
+            nfs_config: nfs_config.as_ref(),
+            swarm_names,
+            backends,
+            targeted,
+            values,
+            locked_digests: LockProcessor::load(Path::new(repo_path))?,
+        };
+
+        let mut report = ReconcileReport::new();
+        // Stacks (by Swarm name) that failed or were blocked: their dependents are not deployed
+        let mut blocked: HashSet<String> = HashSet::new();
+
+        for level in &levels {
+            let mut ready: Vec<&StackDefinition> = Vec::new();
+            let mut health_checked: HashMap<String, Result<(), String>> = HashMap::new();
+
+            for stack_def in stacks_definitions.iter().filter(|s| level.contains(&s.name)) {
+                match self.check_dependencies(stack_def, &ctx, &blocked, &mut health_checked).await {
+                    Ok(()) => ready.push(stack_def),
+                    Err(outcome) => {
+                        println!("  [{}] Not deployed: {}", stack_def.swarm_name(), outcome.detail());
+                        blocked.insert(stack_def.swarm_name().to_string());
+                        report.record(repository_url, Some(stack_def.swarm_name()), outcome);
+                    }
+                }
+            }
+
+            let level_report = self.process_stacks_concurrently(&ready, &ctx).await?;
+            blocked.extend(level_report.failed_stacks());
+            report.extend(level_report);
+
+            // Remember dependencies so that `stop` can remove stacks in reverse order
+            for stack_def in &ready {
+                let depends_on: Vec<&str> = stack_def.depends_on.iter().map(|d| ctx.swarm_name(d.name())).collect();
+                self.db.update_stack_dependencies(stack_def.swarm_name(), repository_url, &self.cluster.name, &depends_on.join(",")).await?;
+            }
         }
 
-        Ok(())
+        Ok(report)
     }
 
-    /// Traite un stack individuel
-    async fn process_stack(
+    /// Vérifie qu'un stack peut être déployé : aucune dépendance en échec et, si demandé,
+    /// dépendances en bonne santé. Les attentes de santé sont mémorisées pour la vague courante.
+    async fn check_dependencies(
         &self,
         stack_def: &StackDefinition,
-        repo_path: &str,
-        repository_url: &str,
-        is_reconcile: bool,
-        force: bool,
-        volumes_definitions: Option<&[crate::models::VolumeDefinition]>,
-        nfs_config: Option<&crate::models::NfsConfig>,
-    ) -> Result<()> {
+        ctx: &RepoContext<'_>,
+        blocked: &HashSet<String>,
+        health_checked: &mut HashMap<String, Result<(), String>>,
+    ) -> std::result::Result<(), Outcome> {
+        for dep in &stack_def.depends_on {
+            let dep_name = ctx.swarm_name(dep.name());
+            if !ctx.targeted.contains(dep_name) {
+                return Err(Outcome::Failed(format!("dependency '{}' is not deployed to cluster '{}'", dep_name, self.cluster.name)));
+            }
+            if blocked.contains(dep_name) {
+                return Err(Outcome::Skipped(format!("dependency '{}' failed", dep_name)));
+            }
+            if !dep.wait_healthy() {
+                continue;
+            }
+
+            if !health_checked.contains_key(dep_name) {
+                let result = self.cluster.stack_service.wait_until_healthy(ctx.backend(dep_name), dep_name, dep.timeout()).await
+                    .map_err(|e| format!("{:#}", e));
+                health_checked.insert(dep_name.to_string(), result);
+            }
+            if let Some(Err(e)) = health_checked.get(dep_name) {
+                return Err(Outcome::Failed(format!("dependency '{}' is not healthy: {}", dep_name, e)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Traite un lot de stacks en parallèle (au plus `stack_concurrency` à la fois), en isolant les échecs
+    async fn process_stacks_concurrently(&self, stacks: &[&StackDefinition], ctx: &RepoContext<'_>) -> Result<ReconcileReport> {
+        let results: Vec<(&StackDefinition, Result<Outcome>)> = stream::iter(stacks.iter().copied())
+            .map(|stack_def| async move { (stack_def, self.process_stack(stack_def, ctx).await) })
+            .buffered(self.config.stack_concurrency)
+            .collect()
+            .await;
+
+        let mut report = ReconcileReport::new();
+        for (stack_def, result) in results {
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    eprintln!("  [{}] Error processing stack: {:#}", stack_def.swarm_name(), e);
+                    self.db.update_stack_status(stack_def.swarm_name(), ctx.repository_url, &self.cluster.name, "error").await?;
+                    Outcome::Failed(format!("{:#}", e))
+                }
+            };
+            report.record(ctx.repository_url, Some(stack_def.swarm_name()), outcome);
+        }
+
+        Ok(report)
+    }
+
+    /// Traite un stack individuel
+    async fn process_stack(&self, stack_def: &StackDefinition, ctx: &RepoContext<'_>) -> Result<Outcome> {
+        let repository_url = ctx.repository_url;
+        let stack_name = stack_def.swarm_name();
+        let backend = ctx.backend(stack_name);
+
+        if !stack_def.enabled {
+            println!("  [{}] Disabled in stacks.yaml, skipping", stack_name);
+            // A disabled stack is not removed: its images stay in use until it is stopped
+            if ctx.count_images {
+                self.count_deployed_images(stack_name, repository_url).await?;
+            }
+            return Ok(Outcome::Skipped("disabled".to_string()));
+        }
+
         // Look for the stack directory
-        let stack_dir = Path::new(repo_path).join(&stack_def.name);
+        let stack_dir = Self::stack_directory(ctx.repo_path, stack_def)?;
         if !stack_dir.exists() || !stack_dir.is_dir() {
-            eprintln!("Warning: Stack directory '{}' not found", stack_def.name);
-            return Ok(());
+            eprintln!("  [{}] Warning: Stack directory '{}' not found", stack_name, stack_def.directory());
+            return Ok(Outcome::Skipped("stack directory not found".to_string()));
         }
 
-        // Look for docker-compose file in the stack directory
-        let compose_files = vec![
-            stack_dir.join("docker-compose.yml"),
-            stack_dir.join("docker-compose.yaml"),
-            stack_dir.join("compose.yml"),
-            stack_dir.join("compose.yaml"),
-        ];
+        let environment = self.config.environment.as_deref();
 
-        let compose_file_path = compose_files.iter()
-            .find(|f| f.exists())
-            .ok_or_else(|| anyhow::anyhow!("No docker-compose file found in stack directory '{}'", stack_def.name))?;
+        // Several compose files are merged in order; relative paths resolve against the first one, as with Compose
+        let compose_file_paths = Self::compose_files(&stack_dir, stack_def, environment)?;
+        let compose_file_path = &TemplateProcessor::rendered_path(&compose_file_paths[0]);
+        let (mut compose_content, included_files) = self.render_compose(stack_def, &stack_dir, ctx.repo_path, &ctx.values)?;
 
-        let mut compose_content = fs::read_to_string(compose_file_path)?;
+        // Interpolate ${VAR} from .env files and allowlisted daemon variables before any transformation
+        let (interpolated, unset) = self.interpolate_variables(&stack_dir, ctx.repo_path, &compose_content)?;
+        for name in unset {
+            println!("  [{}] Warning: variable '{}' is not set, substituting an empty string", stack_name, name);
+        }
+        compose_content = interpolated;
 
         // Process volumes in compose file if volumes definitions exist
-        if let Some(volumes_defs) = volumes_definitions {
-            if let Some(nfs_config) = nfs_config {
+        if let Some(volumes_defs) = ctx.volumes_definitions {
+            if let Some(nfs_config) = ctx.nfs_config {
                 compose_content = ComposeProcessor::process_volumes(&compose_content, volumes_defs, nfs_config)?;
             }
         }
 
-        // Process secrets: read secrets.yaml (declaration only), generate entrypoint-secrets.sh, inject into compose
-        if let Some(secret_defs) = SecretProcessor::process_secrets(&stack_dir)? {
+        // Process secrets: manifest or secrets.yaml (declaration only), generate entrypoint-secrets.sh, inject into compose
+        if let Some(secret_defs) = SecretProcessor::process_secrets(&stack_dir, stack_def.secrets.as_deref())? {
             let entrypoint_volume = "./entrypoint-secrets.sh:/run/entrypoint-secrets.sh:ro";
             compose_content = ComposeProcessor::process_secrets(&compose_content, &secret_defs, entrypoint_volume)?;
         }
 
+        // Pin images to their digest, so that the stack hash changes when a tag is moved upstream
+        let unpinned_content = compose_content.clone();
+        let image_digests = self.pinned_digests(stack_name, &compose_content, ctx).await?;
+        if !image_digests.is_empty() {
+            compose_content = ComposeProcessor::pin_images(&compose_content, &image_digests)?;
+        }
+
         // Write the modified compose content back to the file
         fs::write(compose_file_path, &compose_content)?;
 
-        let compose_hash = Self::calculate_md5(&compose_content);
+        // The stack digest covers the compose, included files and every file mounted or referenced by a service
+        let compose_dir = compose_file_path.parent().unwrap_or(&stack_dir);
+        let repo_root = ComposeProcessor::normalize_path(Path::new(ctx.repo_path));
+        let mut roots = vec![repo_root.as_path()];
+        if let Some(nfs_config) = ctx.nfs_config {
+            roots.push(Path::new(&nfs_config.path));
+        }
+        let service_digests = DigestProcessor::service_digests(&compose_content, compose_dir, &roots)?;
+        let compose_hash = DigestProcessor::stack_digest(&compose_content, &included_files, &service_digests)?;
+        let service_digests_json = serde_json::to_string(&service_digests)?;
 
         // Calculate relative path for database
         let relative_compose_path = compose_file_path
-            .strip_prefix(repo_path)
+            .strip_prefix(ctx.repo_path)
             .unwrap_or(compose_file_path)
             .to_string_lossy()
             .replace('\\', "/")
             .to_string();
 
+        // Tags resolved here (not those of dockerops.lock) keep being watched by image-watch once pinned
+        let resolved_images: Vec<&String> = image_digests.keys()
+            .filter(|image| !ctx.locked_digests.contains_key(*image))
+            .collect();
+        let mut deployment = Deployment::new(
+            stack_name.to_string(),
+            repository_url.to_string(),
+            ctx.commit_sha.clone(),
+            compose_content.clone(),
+        );
+        deployment.resolved_images = serde_json::to_string(&resolved_images)?;
+
+        // Count image references before deploying, so that a failed or skipped deployment keeps its images
+        if ctx.count_images {
+            self.process_yaml_file(stack_name, &unpinned_content, &relative_compose_path).await?;
+        }
+
+        // Suspended stacks are only checked for drift, never deployed
+        if let Some(suspension) = self.db.get_active_suspension(stack_name, repository_url).await? {
+            let deployed_hash = self.db.get_stack_by_name(stack_name, repository_url, &self.cluster.name).await?.map(|s| s.hash);
+            let until = suspension.expires_at.as_deref().unwrap_or("resumed");
+            if deployed_hash.as_deref() == Some(compose_hash.as_str()) {
+                println!("  [{}] Suspended ({} '{}', until {}): no drift", stack_name, suspension.kind, suspension.target, until);
+            } else {
+                println!("  [{}] Suspended ({} '{}', until {}): drift detected, repository differs from the deployed stack", stack_name, suspension.kind, suspension.target, until);
+            }
+
+            return Ok(Outcome::Skipped(format!("suspended until {}", until)));
+        }
+
         // Check if stack exists in database
-        if let Some(existing_stack) = self.db.get_stack_by_name(&stack_def.name, repository_url).await? {
-            let has_changed = existing_stack.hash != compose_hash;
-            let should_deploy = has_changed || force;
+        let outcome = if let Some(existing_stack) = self.db.get_stack_by_name(stack_name, repository_url, &self.cluster.name).await? {
+            // Stacks recorded with the former MD5 hash are not redeployed just because the algorithm changed
+            if !ctx.force
+                && existing_stack.status != "error"
+                && Self::is_legacy_hash(&existing_stack.hash, &unpinned_content, &included_files)?
+            {
+                self.db.update_stack_hash(stack_name, repository_url, &self.cluster.name, &compose_hash).await?;
+                self.db.update_stack_service_digests(stack_name, repository_url, &self.cluster.name, &service_digests_json).await?;
+                return Ok(Outcome::Unchanged);
+            }
 
-            if should_deploy {
-                if is_reconcile {
-                    // For reconcile, stop the existing stack first
-                    self.stack_service.stop_stack(&stack_def.name).await?;
+            let has_changed = existing_stack.hash != compose_hash;
+            // Switching between Swarm and Compose redeploys the stack from scratch
+            let previous_backend = existing_stack.deploy_backend();
+            let backend_changed = previous_backend != backend;
+            // A stack whose last deployment failed is retried even if its hash is unchanged
+            let should_deploy = has_changed || backend_changed || ctx.force || existing_stack.status == "error";
+
+            if existing_stack.pinned {
+                if should_deploy {
+                    println!("  [{}] Pinned by a rollback, skipping deployment (run 'dockerops unpin {}' to resume)", stack_name, stack_name);
+                }
+                Outcome::Skipped("pinned by a rollback".to_string())
+            } else if should_deploy {
+                // When only mounted files changed, the services are updated in place and restarted one by one
+                let deployed_compose = self.db.get_successful_deployments(stack_name, repository_url, &self.cluster.name).await?
+                    .into_iter().next().map(|d| d.rendered_compose);
+                let inputs_only = !ctx.force
+                    && !backend_changed
+                    && existing_stack.status != "error"
+                    && deployed_compose.as_deref() == Some(compose_content.as_str());
+
+                // Services are updated in place; a stack moving to another backend is removed from the previous one first
+                if backend_changed {
+                    self.cluster.stack_service.stop_stack(previous_backend, stack_name).await?;
                 }
 
                 // Update stack in database
-                self.db.update_stack_hash(&stack_def.name, repository_url, &compose_hash).await?;
+                self.db.update_stack_hash(stack_name, repository_url, &self.cluster.name, &compose_hash).await?;
+                self.db.update_stack_backend(stack_name, repository_url, &self.cluster.name, backend.as_str()).await?;
 
                 // Deploy the updated stack
-                self.deploy_stack(&stack_def.name, compose_file_path).await?;
-                self.db.update_stack_status(&stack_def.name, repository_url, "deployed").await?;
+                self.deploy_and_record(backend, deployment, compose_file_path).await?;
+
+                if inputs_only {
+                    let previous_digests = existing_stack.service_digests_map();
+                    for (service, digest) in &service_digests {
+                        if previous_digests.get(service) != Some(digest) {
+                            self.cluster.stack_service.force_update_service(backend, stack_name, service).await?;
+                        }
+                    }
+                }
+                self.db.update_stack_service_digests(stack_name, repository_url, &self.cluster.name, &service_digests_json).await?;
+                Outcome::Deployed
+            } else {
+                Outcome::Unchanged
             }
         } else {
             // New stack
-            let stack = Stack::new(
-                stack_def.name.clone(),
+            let mut stack = Stack::new(
+                stack_name.to_string(),
                 repository_url.to_string(),
                 relative_compose_path.clone(),
                 compose_hash.clone(),
             );
+            stack.service_digests = service_digests_json;
+            stack.backend = backend.as_str().to_string();
+            stack.cluster = self.cluster.name.clone();
             self.db.create_stack(&stack).await?;
 
             // Deploy the new stack
-            self.deploy_stack(&stack_def.name, compose_file_path).await?;
-            self.db.update_stack_status(&stack_def.name, repository_url, "deployed").await?;
+            self.deploy_and_record(backend, deployment, compose_file_path).await?;
+            Outcome::Deployed
+        };
+
+        Ok(outcome)
+    }
+
+    /// Rend les fichiers compose d'un stack et les fusionne : templates (.tmpl) avec les valeurs du repository
+    /// surchargées par le values.yaml du stack, puis include/extends. Retourne le compose et les fichiers inclus.
+    fn render_compose(
+        &self,
+        stack_def: &StackDefinition,
+        stack_dir: &Path,
+        repo_path: &str,
+        repo_values: &serde_yaml::Value,
+    ) -> Result<(String, Vec<PathBuf>)> {
+        let environment = self.config.environment.as_deref();
+        let compose_file_paths = Self::compose_files(stack_dir, stack_def, environment)?;
+
+        let values = TemplateProcessor::merge_values(
+            repo_values.clone(),
+            TemplateProcessor::load_values(stack_dir, environment)?,
+        );
+        let template_context = TemplateProcessor::context(&values, environment, Some(stack_def.swarm_name()));
+        // Each file's include/extends are resolved relative to its own directory before merging
+        let mut documents = Vec::new();
+        let mut included_files = Vec::new();
+        for path in &compose_file_paths {
+            let content = TemplateProcessor::read(path, &template_context)?;
+            let file_dir = path.parent().unwrap_or(stack_dir);
+            let (resolved, files) = ComposeProcessor::resolve_includes_and_extends(&content, file_dir, Path::new(repo_path))
+                .map_err(|e| anyhow::anyhow!("{}: {:#}", path.display(), e))?;
+            documents.push(resolved);
+            included_files.extend(files);
         }
 
-        // Process compose file for image extraction
-        self.process_yaml_file(&compose_content, &relative_compose_path).await?;
+        let compose_content = match documents.as_slice() {
+            [document] => document.clone(),
+            _ => ComposeProcessor::merge(&documents)?,
+        };
+        Ok((compose_content, included_files))
+    }
+
+    /// Interpole le compose d'un stack avec les variables de ses fichiers .env et celles de DockerOps autorisées
+    fn interpolate_variables(&self, stack_dir: &Path, repo_path: &str, compose_content: &str) -> Result<(String, BTreeSet<String>)> {
+        let variables = EnvProcessor::load_variables(
+            Path::new(repo_path),
+            stack_dir,
+            self.config.environment.as_deref(),
+            &self.config.env_allowlist,
+        )?;
+        EnvProcessor::interpolate(compose_content, &variables)
+    }
+
+    /// Vérifie avant tout déploiement que les variables obligatoires (`${VAR:?}`) des stacks à déployer sont définies.
+    /// Les autres erreurs de rendu sont laissées au traitement de chaque stack.
+    fn check_required_variables(&self, stacks: &[&StackDefinition], repo_path: &str, repo_values: &serde_yaml::Value) -> Result<()> {
+        let mut errors = Vec::new();
+        for stack_def in stacks.iter().filter(|s| s.enabled) {
+            let Ok(stack_dir) = Self::stack_directory(repo_path, stack_def) else {
+                continue;
+            };
+            if !stack_dir.is_dir() {
+                continue;
+            }
+            let Ok((compose_content, _)) = self.render_compose(stack_def, &stack_dir, repo_path, repo_values) else {
+                continue;
+            };
+            if let Err(e) = self.interpolate_variables(&stack_dir, repo_path, &compose_content) {
+                errors.push(format!("{}: {:#}", stack_def.swarm_name(), e));
+            }
+        }
 
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!("Invalid repository configuration, nothing deployed:\n  {}", errors.join("\n  ")));
+        }
         Ok(())
     }
 
-    /// Déploie un stack
-    async fn deploy_stack(&self, stack_name: &str, compose_path: &Path) -> Result<()> {
+    /// Dossier d'un stack, qui doit rester à l'intérieur du repository
+    pub fn stack_directory(repo_path: &str, stack_def: &StackDefinition) -> Result<PathBuf> {
+        if !Self::is_inner_path(stack_def.directory()) {
+            return Err(anyhow::anyhow!("Stack path '{}' must be relative to the repository root", stack_def.directory()));
+        }
+        Ok(Path::new(repo_path).join(stack_def.directory()))
+    }
+
+    /// Indique si un chemin relatif reste sous son dossier de base (ni absolu, ni `..`)
+    fn is_inner_path(path: &str) -> bool {
+        Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    }
+
+    /// Fichiers compose d'un stack dans l'ordre de fusion, overlays de l'environnement compris
+    pub fn compose_files(stack_dir: &Path, stack_def: &StackDefinition, environment: Option<&str>) -> Result<Vec<PathBuf>> {
+        let mut compose_file_paths = Self::find_compose_files(stack_dir, stack_def)?;
+        for overlay in OverlayProcessor::compose_overlays(stack_dir, environment) {
+            if !compose_file_paths.contains(&overlay) {
+                compose_file_paths.push(overlay);
+            }
+        }
+        Ok(compose_file_paths)
+    }
+
+    /// Fichiers compose d'un stack : ceux déclarés dans `compose_files`, sinon le premier nom standard trouvé
+    /// (ou son template `.tmpl`)
+    fn find_compose_files(stack_dir: &Path, stack_def: &StackDefinition) -> Result<Vec<PathBuf>> {
+        if stack_def.compose_files.is_empty() {
+            return COMPOSE_FILE_NAMES.iter()
+                .find_map(|name| TemplateProcessor::existing_file(&stack_dir.join(name)))
+                .map(|path| vec![path])
+                .ok_or_else(|| anyhow::anyhow!("No docker-compose file found in stack directory '{}'", stack_def.directory()));
+        }
+
+        stack_def.compose_files.iter()
+            .map(|compose_file| {
+                if !Self::is_inner_path(compose_file) {
+                    return Err(anyhow::anyhow!("Compose file '{}' must be relative to the stack directory '{}'", compose_file, stack_def.directory()));
+                }
+                let path = stack_dir.join(compose_file);
+                if !path.is_file() {
+                    return Err(anyhow::anyhow!("Compose file '{}' not found in stack directory '{}'", compose_file, stack_def.directory()));
+                }
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Déploie un stack en enregistrant la tentative dans l'historique des déploiements
+    async fn deploy_and_record(&self, backend: DeployBackend, mut deployment: Deployment, compose_path: &Path) -> Result<()> {
+        deployment.cluster = self.cluster.name.clone();
+        let deployment_id = self.db.create_deployment(&deployment).await?;
+        let (stack_name, repository_url) = (deployment.stack_name.as_str(), deployment.repository_url.as_str());
+
+        match self.deploy_stack(backend, stack_name, compose_path).await {
+            Ok(image_digests) => {
+                let digests_json = serde_json::to_string(&image_digests)?;
+                self.db.finish_deployment(deployment_id, "succeeded", &digests_json, None).await?;
+                self.db.update_stack_status(stack_name, repository_url, &self.cluster.name, "deployed").await?;
+                Ok(())
+            }
+            Err(e) => {
+                self.db.finish_deployment(deployment_id, "failed", "{}", Some(&e.to_string())).await?;
+                self.db.update_stack_status(stack_name, repository_url, &self.cluster.name, "error").await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Redéploie le compose rendu d'un déploiement précédent avec ses images épinglées par digest,
+    /// puis épingle le stack pour que le prochain reconcile ne l'écrase pas
+    pub async fn rollback_stack(&self, stack: &Stack, target: &Deployment) -> Result<()> {
+        let image_digests = target.image_digests_map();
+        let pinned_compose = ComposeProcessor::pin_images(&target.rendered_compose, &image_digests)?;
+
+        println!("Rolling back stack '{}' to deployment #{} (commit {})",
+            stack.name, target.id, target.commit_sha.as_deref().unwrap_or("unknown"));
+
+        // Services are updated in place: if the rollback fails, the current version keeps running
+        let deployment = Deployment::new(
+            stack.name.clone(),
+            stack.repository_url.clone(),
+            target.commit_sha.clone(),
+            pinned_compose.clone(),
+        );
+        let repo_path = self.checkout_compose(stack, target.commit_sha.as_deref(), &pinned_compose).await?;
+        let result = self.deploy_and_record(stack.deploy_backend(), deployment, &Path::new(&repo_path).join(&stack.compose_path)).await;
+        Self::remove_checkout(&repo_path);
+        result?;
+
+        // Store the hash of what is actually running so that unpinning triggers a redeploy from git
+        self.db.update_stack_hash(&stack.name, &stack.repository_url, &stack.cluster, &DigestProcessor::sha256_hex(pinned_compose.as_bytes())).await?;
+        self.db.set_stack_pinned(&stack.name, &stack.repository_url, &stack.cluster, true).await?;
+        println!("Stack '{}' is now pinned; run 'dockerops unpin {}' to resume reconciliation", stack.name, stack.name);
+
+        Ok(())
+    }
+
+    /// Clone le repository d'un stack au commit `commit_sha` et y écrit `compose_content` à l'emplacement
+    /// d'origine du compose, avec entrypoint-secrets.sh : les chemins relatifs (bind mounts, fichiers des secrets
+    /// et configs) se résolvent comme lors du déploiement. Retourne le chemin du clone, à supprimer après usage.
+    async fn checkout_compose(&self, stack: &Stack, commit_sha: Option<&str>, compose_content: &str) -> Result<String> {
+        let repo_path = self.repo_service.clone_repository(&stack.repository_url).await?;
+        if let Err(e) = self.write_compose(stack, commit_sha, &repo_path, compose_content) {
+            Self::remove_checkout(&repo_path);
+            return Err(e);
+        }
+        Ok(repo_path)
+    }
+
+    /// Écrit un compose rendu dans le clone `repo_path`, placé au commit `commit_sha`
+    fn write_compose(&self, stack: &Stack, commit_sha: Option<&str>, repo_path: &str, compose_content: &str) -> Result<()> {
+        if let Some(commit) = commit_sha {
+            RepositoryService::checkout_revision(repo_path, commit)?;
+        }
+
+        // entrypoint-secrets.sh is generated at render time, it is not part of the repository
+        let manifest = ManifestProcessor::load(Path::new(repo_path), self.config.environment.as_deref(), self.config.discovery.as_ref())?;
+        if let Some(stack_def) = manifest.stacks.iter().find(|s| s.swarm_name() == stack.name) {
+            let stack_dir = Self::stack_directory(repo_path, stack_def)?;
+            if stack_dir.is_dir() {
+                SecretProcessor::process_secrets(&stack_dir, stack_def.secrets.as_deref())?;
+            }
+        }
+
+        let compose_path = Path::new(repo_path).join(&stack.compose_path);
+        if let Some(compose_dir) = compose_path.parent() {
+            fs::create_dir_all(compose_dir)?;
+        }
+        fs::write(&compose_path, compose_content)?;
+        Ok(())
+    }
+
+    /// Supprime un clone temporaire du repository
+    fn remove_checkout(repo_path: &str) {
+        if let Err(e) = fs::remove_dir_all(repo_path) {
+            println!("Warning: Could not clean up repository directory: {}", e);
+        }
+    }
+
+    /// Surveillance des images : résout le digest actuel des tags du dernier déploiement réussi d'un stack et,
+    /// si un tag a été déplacé dans son registry, met à jour les seuls services qui l'utilisent. Les tags épinglés
+    /// par DockerOps au rendu sont surveillés aussi ; ceux de dockerops.lock ou épinglés dans le compose ne le sont pas.
+    /// La mise à jour est enregistrée comme un nouveau déploiement, avec le compose rendu épinglé aux nouveaux digests.
+    pub async fn refresh_images(&self, stack: &Stack) -> Result<Outcome> {
+        if stack.pinned {
+            return Ok(Outcome::Skipped("pinned".to_string()));
+        }
+        if stack.status != "deployed" {
+            return Ok(Outcome::Skipped(format!("status is {}", stack.status)));
+        }
+        if let Some(suspension) = self.db.get_active_suspension(&stack.name, &stack.repository_url).await? {
+            return Ok(Outcome::Skipped(format!("suspended until {}", suspension.expires_at.as_deref().unwrap_or("resumed"))));
+        }
+        let Some(deployment) = self.db.get_successful_deployments(&stack.name, &stack.repository_url, &stack.cluster).await?
+            .into_iter().next()
+        else {
+            return Ok(Outcome::Skipped("no successful deployment".to_string()));
+        };
+
+        // Digests are recorded under the tag reference, without the '@digest' of pinned images
+        let recorded = deployment.image_digests_map();
+        let mut resolved_images = deployment.resolved_images_set();
+        let service_images = ComposeProcessor::service_images(&deployment.rendered_compose)?;
+        let mut moved = BTreeMap::new();
+        for (image, reference, previous) in Self::watched_images(&service_images, &recorded, &resolved_images) {
+            match self.cluster.image_service.resolve_digest(reference).await {
+                Ok(digest) if digest != *previous => {
+                    println!("  [{}] Image {} moved from {} to {}", stack.name, reference, previous, digest);
+                    moved.insert(image.clone(), digest);
+                }
+                Ok(_) => {}
+                Err(e) => println!("  [{}] Warning: {:#}", stack.name, e),
+            }
+        }
+        if moved.is_empty() {
+            return Ok(Outcome::Unchanged);
+        }
+
+        let mut image_digests = recorded;
+        for (image, digest) in &moved {
+            let reference = Self::tag_reference(image).to_string();
+            image_digests.insert(reference.clone(), digest.clone());
+            resolved_images.insert(reference);
+        }
+        let mut record = Deployment::new(
+            stack.name.clone(),
+            stack.repository_url.clone(),
+            deployment.commit_sha.clone(),
+            ComposeProcessor::pin_images(&deployment.rendered_compose, &moved)?,
+        );
+        record.resolved_images = serde_json::to_string(&resolved_images)?;
+        record.cluster = stack.cluster.clone();
+        let deployment_id = self.db.create_deployment(&record).await?;
+
+        match self.update_moved_images(stack, &record, &service_images, &moved).await {
+            Ok(()) => {
+                self.db.finish_deployment(deployment_id, "succeeded", &serde_json::to_string(&image_digests)?, None).await?;
+                Ok(Outcome::Deployed)
+            }
+            Err(e) => {
+                self.db.finish_deployment(deployment_id, "failed", "{}", Some(&e.to_string())).await?;
+                self.db.update_stack_status(&stack.name, &stack.repository_url, &stack.cluster, "error").await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Images du compose rendu surveillées, avec leur référence par tag et le digest enregistré : les tags et
+    /// les références épinglées par DockerOps au rendu (`resolved`), pas celles de dockerops.lock ou du compose
+    fn watched_images<'a>(
+        service_images: &'a BTreeMap<String, String>,
+        recorded: &'a BTreeMap<String, String>,
+        resolved: &BTreeSet<String>,
+    ) -> Vec<(&'a String, &'a str, &'a String)> {
+        let images: BTreeSet<&String> = service_images.values()
+            .filter(|image| !image.contains('@') || resolved.contains(Self::tag_reference(image)))
+            .collect();
+        images.into_iter()
+            .filter_map(|image| {
+                let reference = Self::tag_reference(image);
+                recorded.get(reference).map(|previous| (image, reference, previous))
+            })
+            .collect()
+    }
+
+    /// Référence par tag d'une image, sans son éventuel `@digest`
+    fn tag_reference(image: &str) -> &str {
+        image.split('@').next().unwrap_or(image)
+    }
+
+    /// Met à jour les services dont l'image a été déplacée (`moved` : image du compose -> nouveau digest).
+    /// En mode compose, les conteneurs sont recréés depuis le compose rendu de `record`, comme au déploiement.
+    async fn update_moved_images(
+        &self,
+        stack: &Stack,
+        record: &Deployment,
+        service_images: &BTreeMap<String, String>,
+        moved: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let services = service_images.iter()
+            .filter_map(|(service, image)| moved.get(image).map(|digest| (service, format!("{}@{}", Self::tag_reference(image), digest))));
+
+        if stack.deploy_backend() == DeployBackend::Swarm {
+            for (service, pinned) in services {
+                self.cluster.stack_service.update_service_image(&stack.name, service, &pinned).await?;
+            }
+            return Ok(());
+        }
+
+        // Compose containers are created from the local image, which must be pulled first
+        for (image, digest) in moved {
+            self.cluster.image_service.force_pull_image(&format!("{}@{}", Self::tag_reference(image), digest)).await?;
+        }
+        let repo_path = self.checkout_compose(stack, record.commit_sha.as_deref(), &record.rendered_compose).await?;
+        let compose_path = Path::new(&repo_path).join(&stack.compose_path);
+        let mut result = Ok(());
+        for (service, _) in services {
+            result = self.cluster.stack_service.recreate_service(&stack.name, service, &compose_path).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        Self::remove_checkout(&repo_path);
+        result
+    }
+
+    /// Digests auxquels épingler les images d'un compose : ceux de dockerops.lock, puis ceux résolus auprès
+    /// du registry, sauf si `DOCKEROPS_PIN_DIGESTS=false`. Les références déjà épinglées sont laissées telles quelles.
+    /// Une image que le registry ne peut pas résoudre est épinglée à son digest local, à défaut déployée par tag.
+    async fn pinned_digests(&self, stack_name: &str, compose_content: &str, ctx: &RepoContext<'_>) -> Result<BTreeMap<String, String>> {
+        let images: BTreeSet<String> = ComposeProcessor::extract_images(compose_content)?
+            .into_iter()
+            .filter(|image| !image.contains('@'))
+            .collect();
+
+        let mut image_digests = BTreeMap::new();
+        let mut unresolved = Vec::new();
+        for image in images {
+            match ctx.locked_digests.get(&image) {
+                Some(digest) => {
+                    image_digests.insert(image, digest.clone());
+                }
+                None => unresolved.push(image),
+            }
+        }
+
+        if self.config.pin_digests {
+            let resolved: Vec<Result<Option<(String, String)>>> = stream::iter(unresolved)
+                .map(|image| async move {
+                    let digest = match self.cluster.image_service.resolve_digest(&image).await {
+                        Ok(digest) => Some(digest),
+                        // A local-only image or an unreachable registry does not block the deployment
+                        Err(e) => match self.cluster.image_service.get_image_digest(&image).await? {
+                            Some(digest) => {
+                                println!("  [{}] Warning: {:#}, pinning {} to its local digest", stack_name, e, image);
+                                Some(digest)
+                            }
+                            None => {
+                                println!("  [{}] Warning: {:#}, deploying {} unpinned", stack_name, e, image);
+                                None
+                            }
+                        },
+                    };
+                    Ok(digest.map(|digest| (image, digest)))
+                })
+                .buffer_unordered(self.config.pull_concurrency)
+                .collect()
+                .await;
+            for result in resolved {
+                if let Some((image, digest)) = result? {
+                    image_digests.insert(image, digest);
+                }
+            }
+        }
+
+        if !image_digests.is_empty() {
+            println!("  [{}] Pinned {} image(s) to their digest", stack_name, image_digests.len());
+        }
+        Ok(image_digests)
+    }
+
+    /// Déploie un stack et retourne les digests des images utilisées
+    async fn deploy_stack(&self, backend: DeployBackend, stack_name: &str, compose_path: &Path) -> Result<BTreeMap<String, String>> {
         // Read compose file to extract images
         let compose_content = fs::read_to_string(compose_path)?;
 
         // Extract and pull images before deployment
         let images_found = ComposeProcessor::extract_images(&compose_content)?;
 
-        if !images_found.is_empty() {
-            for image_name in &images_found {
-                self.image_service.pull_image(image_name).await?;
+        let pulled: Vec<Result<Option<(String, String)>>> = stream::iter(images_found)
+            .map(|image_name| async move {
+                self.cluster.image_service.pull_image(&image_name).await?;
+                // A pinned reference already names its digest: record it under the reference without it
+                if let Some((reference, digest)) = image_name.split_once('@') {
+                    return Ok(Some((reference.to_string(), digest.to_string())));
+                }
+                let digest = self.cluster.image_service.get_image_digest(&image_name).await?;
+                Ok(digest.map(|digest| (image_name, digest)))
+            })
+            .buffer_unordered(self.config.pull_concurrency)
+            .collect()
+            .await;
+
+        let mut image_digests = BTreeMap::new();
+        for result in pulled {
+            if let Some((image_name, digest)) = result? {
+                image_digests.insert(image_name, digest);
             }
         }
 
         // Deploy the stack using Docker client (no secret values; secrets are Swarm-native)
-        self.stack_service.deploy_stack(stack_name, compose_path).await?;
+        self.cluster.stack_service.deploy_stack(backend, stack_name, compose_path).await?;
 
-        Ok(())
+        Ok(image_digests)
     }
 
     /// Traite un fichier YAML pour extraire les images et mettre à jour la base de données
-    async fn process_yaml_file(&self, content: &str, file_path: &str) -> Result<()> {
+    async fn process_yaml_file(&self, stack_name: &str, content: &str, file_path: &str) -> Result<()> {
 
         // Extract images from YAML structure
         let images_found = ComposeProcessor::extract_images(content)?;
 
         // Update database with found images
         for image_name in &images_found {
-            self.update_image_reference(image_name).await?;
+            self.db.increment_image_reference(image_name).await?;
         }
 
         if !images_found.is_empty() {
-            println!("  Found {} images in {}: {:?}", images_found.len(), file_path, images_found);
+            println!("  [{}] Found {} images in {}: {:?}", stack_name, images_found.len(), file_path, images_found);
         }
 
         Ok(())
     }
 
-    /// Met à jour la référence d'une image dans la base de données
-    async fn update_image_reference(&self, image_name: &str) -> Result<()> {
-        use crate::models::Image;
-
-        // Try to get existing image
-        if let Some(existing_image) = self.db.get_image_by_name(image_name).await? {
-            // Increment reference count
-            let new_count = existing_image.reference_count + 1;
-            self.db.update_image_reference_count(image_name, new_count).await?;
-        } else {
-            // Create new image with reference count 1
-            let new_image = Image::new(image_name.to_string(), 1);
-            self.db.create_image(&new_image).await?;
+    /// Compte les images du dernier déploiement réussi d'un stack encore en place (non arrêté),
+    /// sous leur référence non épinglée
+    async fn count_deployed_images(&self, stack_name: &str, repository_url: &str) -> Result<()> {
+        let Some(stack) = self.db.get_stack_by_name(stack_name, repository_url, &self.cluster.name).await? else {
+            return Ok(());
+        };
+        if stack.status == "stopped" {
+            return Ok(());
         }
+        let Some(deployment) = self.db.get_successful_deployments(stack_name, repository_url, &self.cluster.name).await?
+            .into_iter().next()
+        else {
+            return Ok(());
+        };
 
+        for image_name in ComposeProcessor::extract_images(&deployment.rendered_compose)? {
+            let reference = image_name.split_once('@').map_or(image_name.as_str(), |(reference, _)| reference);
+            self.db.increment_image_reference(reference).await?;
+        }
         Ok(())
     }
 
-    /// Calcule le hash MD5 d'un contenu
-    fn calculate_md5(content: &str) -> String {
-        let result = md5::compute(content.as_bytes());
-        format!("{:x}", result)
+    /// Indique si `hash` est l'ancien hash MD5 (compose et fichiers inclus) du même contenu
+    fn is_legacy_hash(hash: &str, compose_content: &str, included_files: &[PathBuf]) -> Result<bool> {
+        if hash.len() != 32 {
+            return Ok(false);
+        }
+        let mut hashed_content = compose_content.to_string();
+        for path in included_files {
+            hashed_content.push_str(&fs::read_to_string(path)?);
+        }
+        Ok(format!("{:x}", md5::compute(hashed_content.as_bytes())) == hash)
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(c: char) -> String {
+        format!("sha256:{}", c.to_string().repeat(64))
+    }
+
+    #[test]
+    fn tag_reference_strips_the_digest() {
+        assert_eq!(StackProcessor::tag_reference("nginx:1.27"), "nginx:1.27");
+        assert_eq!(StackProcessor::tag_reference("localhost:5000/web:latest"), "localhost:5000/web:latest");
+        assert_eq!(StackProcessor::tag_reference(&format!("localhost:5000/web:latest@{}", digest('a'))), "localhost:5000/web:latest");
+        assert_eq!(StackProcessor::tag_reference(&format!("redis@{}", digest('a'))), "redis");
+    }
+
+    #[test]
+    fn watched_images_cover_tags_and_references_resolved_at_render() {
+        let service_images = BTreeMap::from([
+            ("web".to_string(), "nginx:1.27".to_string()),
+            ("app".to_string(), format!("localhost:5000/app:latest@{}", digest('a'))),
+            ("db".to_string(), format!("postgres:16@{}", digest('b'))),
+            ("cache".to_string(), format!("redis:7@{}", digest('c'))),
+            ("worker".to_string(), "busybox:latest".to_string()),
+        ]);
+        // postgres:16 comes from dockerops.lock, redis:7 was pinned in the compose file, busybox was never recorded
+        let recorded = BTreeMap::from([
+            ("nginx:1.27".to_string(), digest('d')),
+            ("localhost:5000/app:latest".to_string(), digest('a')),
+            ("postgres:16".to_string(), digest('b')),
+            ("redis:7".to_string(), digest('c')),
+        ]);
+        let resolved = BTreeSet::from(["localhost:5000/app:latest".to_string()]);
+
+        let watched = StackProcessor::watched_images(&service_images, &recorded, &resolved);
+        let watched: Vec<(&str, &str, &str)> = watched.into_iter()
+            .map(|(image, reference, previous)| (image.as_str(), reference, previous.as_str()))
+            .collect();
+        let pinned_app = format!("localhost:5000/app:latest@{}", digest('a'));
+        assert_eq!(watched, vec![
+            (pinned_app.as_str(), "localhost:5000/app:latest", digest('a').as_str()),
+            ("nginx:1.27", "nginx:1.27", digest('d').as_str()),
+        ]);
+    }
+
+    #[test]
+    fn moved_images_are_pinned_to_their_new_digest() {
+        let compose = format!(
+            "services:\n  web:\n    image: nginx:1.27\n  app:\n    image: localhost:5000/app:latest@{}\n  db:\n    image: postgres:16@{}\n",
+            digest('a'), digest('b'),
+        );
+        let moved = BTreeMap::from([
+            ("nginx:1.27".to_string(), digest('e')),
+            (format!("localhost:5000/app:latest@{}", digest('a')), digest('f')),
+        ]);
+
+        let pinned = ComposeProcessor::pin_images(&compose, &moved).unwrap();
+        let images = ComposeProcessor::service_images(&pinned).unwrap();
+        assert_eq!(images["web"], format!("nginx:1.27@{}", digest('e')));
+        assert_eq!(images["app"], format!("localhost:5000/app:latest@{}", digest('f')));
+        assert_eq!(images["db"], format!("postgres:16@{}", digest('b')));
+    }
+}
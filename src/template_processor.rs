@@ -0,0 +1,108 @@
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_yaml::{Mapping, Value};
+use crate::overlay_processor::OverlayProcessor;
+
+/// Extension des fichiers rendus par le moteur de templates
+pub const TEMPLATE_EXTENSION: &str = "tmpl";
+
+/// Processeur pour les templates (`docker-compose.yml.tmpl`, fichiers de configuration `.tmpl`)
+/// rendus avec les valeurs des fichiers `values.yaml`
+pub struct TemplateProcessor;
+
+impl TemplateProcessor {
+    /// Charge `values.yaml` d'un dossier puis ses overlays d'environnement, fusionnés récursivement
+    pub fn load_values(dir: &Path, environment: Option<&str>) -> Result<Value> {
+        let base = dir.join("values.yaml");
+        let files = base.is_file().then_some(base).into_iter()
+            .chain(OverlayProcessor::overlay_files(dir, "values.yaml", environment));
+
+        let mut values = Value::Mapping(Mapping::new());
+        for path in files {
+            let content = fs::read_to_string(&path)?;
+            let overlay: Value = serde_yaml::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Invalid YAML in {}: {}", path.display(), e))?;
+            values = Self::merge_values(values, overlay);
+        }
+
+        Ok(values)
+    }
+
+    /// Fusionne récursivement deux jeux de valeurs ; les valeurs de `overlay` sont prioritaires
+    pub fn merge_values(base: Value, overlay: Value) -> Value {
+        match (base, overlay) {
+            (Value::Mapping(mut base), Value::Mapping(overlay)) => {
+                for (key, value) in overlay {
+                    let merged = match base.get_mut(&key) {
+                        Some(existing) => Self::merge_values(std::mem::take(existing), value),
+                        None => value,
+                    };
+                    base.insert(key, merged);
+                }
+                Value::Mapping(base)
+            }
+            (_, overlay) => overlay,
+        }
+    }
+
+    /// Contexte de rendu : `values`, `environment` et `stack` (absent pour les fichiers de volumes)
+    pub fn context(values: &Value, environment: Option<&str>, stack: Option<&str>) -> Value {
+        let mut context = Mapping::new();
+        context.insert(Value::from("values"), values.clone());
+        context.insert(Value::from("environment"), environment.map(Value::from).unwrap_or(Value::Null));
+        if let Some(stack) = stack {
+            context.insert(Value::from("stack"), Value::from(stack));
+        }
+        Value::Mapping(context)
+    }
+
+    /// Indique si un fichier doit être rendu
+    pub fn is_template(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == TEMPLATE_EXTENSION)
+    }
+
+    /// Chemin du fichier rendu (extension `.tmpl` retirée)
+    pub fn rendered_path(path: &Path) -> PathBuf {
+        if Self::is_template(path) {
+            path.with_extension("")
+        } else {
+            path.to_path_buf()
+        }
+    }
+
+    /// Le fichier s'il existe, sinon son template `.tmpl` s'il existe
+    pub fn existing_file(path: &Path) -> Option<PathBuf> {
+        if path.is_file() {
+            return Some(path.to_path_buf());
+        }
+        let mut template = path.as_os_str().to_os_string();
+        template.push(format!(".{}", TEMPLATE_EXTENSION));
+        let template = PathBuf::from(template);
+        template.is_file().then_some(template)
+    }
+
+    /// Lit un fichier, rendu s'il s'agit d'un template
+    pub fn read(path: &Path, context: &Value) -> Result<String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        if !Self::is_template(path) {
+            return Ok(content);
+        }
+
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        Self::render(&name, &content, context)
+    }
+
+    /// Rend un template ; une variable non définie est une erreur (utiliser le filtre `default`)
+    pub fn render(name: &str, source: &str, context: &Value) -> Result<String> {
+        let mut env = minijinja::Environment::new();
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+
+        let template = env.template_from_named_str(name, source)
+            .map_err(|e| anyhow::anyhow!("Invalid template {}: {}", name, e))?;
+        template.render(context)
+            .map_err(|e| anyhow::anyhow!("Cannot render template {}: {}", name, e))
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use crate::overlay_processor::OverlayProcessor;
+use crate::template_processor::TemplateProcessor;
 use std::path::Path;
 use std::fs;
 use std::process::Command;
@@ -9,16 +11,8 @@ pub struct VolumeProcessor;
 
 impl VolumeProcessor {
     /// Traite la configuration des volumes depuis un fichier volumes.yaml
-    pub fn load_volumes_config(repo_path: &str) -> Result<Option<Vec<VolumeDefinition>>> {
-        let volumes_file_path = Path::new(repo_path).join("volumes.yaml");
-        if !volumes_file_path.exists() {
-            return Ok(None);
-        }
-
-        let volumes_content = fs::read_to_string(&volumes_file_path)?;
-        let volumes_definitions: Vec<VolumeDefinition> = serde_yaml::from_str(&volumes_content)?;
-
-        Ok(Some(volumes_definitions))
+    pub fn load_volumes_config(repo_path: &str, environment: Option<&str>) -> Result<Option<Vec<VolumeDefinition>>> {
+        OverlayProcessor::load(Path::new(repo_path), "volumes.yaml", Some("id"), environment)
     }
 
     /// Traite tous les volumes définis
@@ -26,6 +20,7 @@ impl VolumeProcessor {
         volumes_definitions: &mut Vec<VolumeDefinition>,
         nfs_config: Option<&NfsConfig>,
         repo_path: &str,
+        template_context: &serde_yaml::Value,
     ) -> Result<()> {
         for volume_def in volumes_definitions {
             match volume_def.r#type {
@@ -35,7 +30,7 @@ impl VolumeProcessor {
                 }
                 VolumeType::Binding => {
                     if let Some(nfs_config) = nfs_config {
-                        Self::process_binding_volume(volume_def, nfs_config, repo_path).await?;
+                        Self::process_binding_volume(volume_def, nfs_config, repo_path, template_context).await?;
                     }
                 }
             }
@@ -44,17 +39,24 @@ impl VolumeProcessor {
         Ok(())
     }
 
-    /// Traite un volume de type binding (copie vers NFS)
+    /// Traite un volume de type binding (copie vers NFS, en rendant les templates `.tmpl`)
     async fn process_binding_volume(
         volume_def: &mut VolumeDefinition,
         nfs_config: &NfsConfig,
         repo_path: &str,
+        template_context: &serde_yaml::Value,
     ) -> Result<()> {
         let local_path = Path::new(repo_path).join(&volume_def.path);
 
-        if !local_path.exists() {
-            return Ok(());
-        }
+        // A single-file binding may be provided as a template (`app.conf.tmpl` for `app.conf`)
+        let local_path = if local_path.is_dir() {
+            local_path
+        } else {
+            match TemplateProcessor::existing_file(&local_path) {
+                Some(path) => path,
+                None => return Ok(()),
+            }
+        };
 
         // Create NFS destination path
         let nfs_dest_path = Path::new(&nfs_config.path).join(&volume_def.path);
@@ -76,12 +78,12 @@ impl VolumeProcessor {
 
         // Copy recursively
         if local_path.is_dir() {
-            Self::copy_directory_recursive(&local_path, &nfs_dest_path).await?;
+            Self::copy_directory_recursive(&local_path, &nfs_dest_path, template_context).await?;
         } else {
             if let Some(parent) = nfs_dest_path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            fs::copy(&local_path, &nfs_dest_path)?;
+            Self::copy_file(&local_path, &nfs_dest_path, template_context)?;
         }
 
         // Fix permissions for Docker compatibility
@@ -94,7 +96,7 @@ impl VolumeProcessor {
     }
 
     /// Copie récursivement un répertoire
-    async fn copy_directory_recursive(src: &Path, dst: &Path) -> Result<()> {
+    async fn copy_directory_recursive(src: &Path, dst: &Path, template_context: &serde_yaml::Value) -> Result<()> {
         if !src.is_dir() {
             return Err(anyhow::anyhow!("Source is not a directory: {}", src.display()));
         }
@@ -108,20 +110,30 @@ impl VolumeProcessor {
             let dst_path = dst.join(entry.file_name());
 
             if file_type.is_dir() {
-                Box::pin(Self::copy_directory_recursive(&src_path, &dst_path)).await?;
+                Box::pin(Self::copy_directory_recursive(&src_path, &dst_path, template_context)).await?;
             } else {
-                fs::copy(&src_path, &dst_path)?;
+                Self::copy_file(&src_path, &TemplateProcessor::rendered_path(&dst_path), template_context)?;
             }
         }
 
         Ok(())
     }
 
+    /// Copie un fichier, ou écrit son rendu s'il s'agit d'un template
+    fn copy_file(src: &Path, dst: &Path, template_context: &serde_yaml::Value) -> Result<()> {
+        if TemplateProcessor::is_template(src) {
+            fs::write(dst, TemplateProcessor::read(src, template_context)?)?;
+        } else {
+            fs::copy(src, dst)?;
+        }
+        Ok(())
+    }
+
     /// Corrige les permissions pour la compatibilité Docker
     async fn fix_permissions_recursive(path: &Path) -> Result<()> {
         // Use chmod command to set appropriate permissions
         let output = Command::new("chmod")
-            .args(&["-R", "755", path.to_str().unwrap()])
+            .args(["-R", "755", path.to_str().unwrap()])
             .output()?;
 
         if !output.status.success() {
@@ -131,7 +143,7 @@ impl VolumeProcessor {
 
         // For files, set 644 permissions
         let output = Command::new("find")
-            .args(&[path.to_str().unwrap(), "-type", "f", "-exec", "chmod", "644", "{}", ";"])
+            .args([path.to_str().unwrap(), "-type", "f", "-exec", "chmod", "644", "{}", ";"])
             .output()?;
 
         if !output.status.success() {
@@ -146,7 +158,7 @@ impl VolumeProcessor {
             .unwrap_or_else(|| "1000".to_string());
 
         let output = Command::new("chown")
-            .args(&["-R", &format!("{}:{}", current_user, current_user), path.to_str().unwrap()])
+            .args(["-R", &format!("{}:{}", current_user, current_user), path.to_str().unwrap()])
             .output()?;
 
         if !output.status.success() {